@@ -1,42 +1,10 @@
+use aoc::md5mine::Miner;
 use aoc::*;
 
 use md5::Digest;
-use smallvec::SmallVec;
 
-use std::iter::{self, once};
-
-fn hash_generator(input: &[u8]) -> impl Iterator<Item = (usize, Digest)> + use<> {
-    let input_len = input.len();
-    let mut n: usize = 1;
-
-    let mut data = SmallVec::<[u8; 24]>::from_slice(input);
-    data.push(b'1');
-
-    once((n, md5::compute(&data))).chain(iter::from_fn(move || {
-        for (pos, x) in data[input_len..].iter_mut().enumerate().rev() {
-            if *x < b'9' {
-                *x += 1;
-                break;
-            } else if pos == 0 {
-                data[input_len..].fill(b'0');
-                data.push(b'0');
-                data[input_len] = b'1';
-                break;
-            } else {
-                *x = b'0';
-            }
-        }
-
-        n += 1;
-        Some((n, md5::compute(&data)))
-    }))
-}
-
-fn find_digest(input: &[u8], f: impl Fn(&Digest) -> bool) -> Result<usize> {
-    hash_generator(input)
-        .find(|(_, digest)| f(digest))
-        .map(|(n, _)| n)
-        .value()
+fn find_digest(input: &[u8], f: impl Fn(&Digest) -> bool + Sync) -> Result<usize> {
+    Miner::new(input, 1, f).next().map(|(n, _)| n).value()
 }
 
 fn main() -> Result<()> {
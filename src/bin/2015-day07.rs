@@ -1,10 +1,11 @@
+use aoc::dag::{Dag, Evaluator};
 use aoc::*;
 
 use eyre::bail;
 use regex::{Regex, RegexSet};
 use smallvec::SmallVec;
 
-use std::collections::{HashMap, VecDeque};
+use std::collections::HashMap;
 
 enum Operand<'a> {
     Constant(u64),
@@ -173,25 +174,6 @@ impl ParseRegex {
     }
 }
 
-fn compute_values<'a>(
-    values: &mut HashMap<&'a str, u64>,
-    graph: &HashMap<&'a str, (Operation<'a>, SmallVec<[&'a str; 2]>)>,
-    inverted_graph: &HashMap<&'a str, Vec<&'a str>>,
-) {
-    let mut queue: VecDeque<_> = graph
-        .iter()
-        .filter(|&(_, (_, dependencies))| dependencies.is_empty())
-        .map(|(&name, _)| name)
-        .collect();
-
-    while let Some(name) = queue.pop_front() {
-        values.insert(name, graph[name].0.value(values));
-        queue.extend(inverted_graph[name].iter().copied().filter(|&x| {
-            (graph[x].1.iter()).all(|&dependencies| values.contains_key(dependencies))
-        }));
-    }
-}
-
 fn main() -> Result<()> {
     let input = setup(file!())?;
     let input = String::from_utf8_lossy(&input);
@@ -205,36 +187,18 @@ fn main() -> Result<()> {
         Regex::new(r#"^(?P<op1>\w+) RSHIFT (?P<op2>\w+) -> (?P<name>\w+)$"#)?,
     )?;
 
-    let mut graph = HashMap::new();
-    let mut inverted_graph = HashMap::<_, Vec<_>>::new();
+    let mut dag = Dag::new();
 
     for line in input.lines() {
         let (name, dependencies, op) = parse_regex.parse(line)?;
-
-        graph.insert(name, (op, dependencies.clone()));
-        inverted_graph.entry(name).or_default();
-
-        for dependency in dependencies {
-            inverted_graph.entry(dependency).or_default().push(name);
-        }
+        dag.insert(name, op, dependencies);
     }
 
-    let mut values = HashMap::new();
-    compute_values(&mut values, &graph, &inverted_graph);
-    let result1 = values["a"];
-
-    values.clear();
-    for &dependency in &graph["b"].1 {
-        let parent = inverted_graph.entry(dependency).or_default();
-        if let Some(position) = parent.iter().position(|&x| x == "b") {
-            parent.remove(position);
-        }
-    }
-    let op = Operation::Identity(Operand::Constant(result1));
-    graph.insert("b", (op, SmallVec::new()));
+    let mut evaluator = Evaluator::new(&dag, Operation::value);
+    let result1 = *evaluator.get("a")?;
 
-    compute_values(&mut values, &graph, &inverted_graph);
-    let result2 = values["a"];
+    evaluator.set("b", result1);
+    let result2 = *evaluator.get("a")?;
 
     println!("{result1}");
     println!("{result2}");
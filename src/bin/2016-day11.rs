@@ -1,14 +1,25 @@
 use aoc::*;
 
-use eyre::bail;
+use eyre::ensure;
 use itertools::Itertools;
 use regex::Regex;
-use smallvec::{SmallVec, smallvec};
+use smallvec::SmallVec;
 
 use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Display};
 use std::hash::{Hash, Hasher};
 
-type IndicesVec = Option<SmallVec<[usize; 2]>>;
+const ELEVATOR_CAPACITY: usize = 2;
+const EXTRA_ELEMENTS: [&str; 2] = ["elerium", "dilithium"];
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum ItemKind {
+    Chip,
+    Generator,
+}
+
+/// Items moved together in the elevator, identified by their pair index
+type Move = SmallVec<[(usize, ItemKind); 4]>;
 
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 struct Pair {
@@ -23,6 +34,20 @@ impl Pair {
             gen_floor,
         }
     }
+
+    fn floor(&self, kind: ItemKind) -> i8 {
+        match kind {
+            ItemKind::Chip => self.chip_floor,
+            ItemKind::Generator => self.gen_floor,
+        }
+    }
+
+    fn floor_mut(&mut self, kind: ItemKind) -> &mut i8 {
+        match kind {
+            ItemKind::Chip => &mut self.chip_floor,
+            ItemKind::Generator => &mut self.gen_floor,
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -56,152 +81,228 @@ impl State {
         }
     }
 
-    fn is_valid(&self) -> bool {
-        (0..=3).contains(&self.elevator_floor)
+    fn is_valid(&self, floor_count: i8) -> bool {
+        (0..floor_count).contains(&self.elevator_floor)
             && self.pairs.iter().all(|p1| {
                 p1.chip_floor == p1.gen_floor
                     || self.pairs.iter().all(|p2| p2.gen_floor != p1.chip_floor)
             })
     }
 
-    fn is_final(&self) -> bool {
-        (self.pairs.iter()).all(|pair| pair.chip_floor == 3 && pair.gen_floor == 3)
+    fn is_final(&self, floor_count: i8) -> bool {
+        (self.pairs.iter())
+            .all(|pair| pair.chip_floor == floor_count - 1 && pair.gen_floor == floor_count - 1)
+    }
+
+    /// Pairs are interchangeable, so sorting them gives the same state for all symmetric states
+    fn canonical(&self) -> Self {
+        let mut state = self.clone();
+        state.pairs.sort_unstable();
+        state
     }
 
-    fn next_states(
-        &self,
-        possible_moves: &[(IndicesVec, IndicesVec)],
-    ) -> impl Iterator<Item = State> {
+    fn next_states<'a>(
+        &'a self,
+        possible_moves: &'a [Move],
+        floor_count: i8,
+    ) -> impl Iterator<Item = (State, &'a Move)> {
         possible_moves
             .iter()
-            .filter(move |(chips, generators)| {
-                if let Some(chips) = chips
-                    && !(chips.iter()).all(|&idx| self.pairs[idx].chip_floor == self.elevator_floor)
-                {
-                    return false;
-                }
+            .filter(move |items| {
+                (items.iter())
+                    .all(|&(index, kind)| self.pairs[index].floor(kind) == self.elevator_floor)
+            })
+            .flat_map(move |items| {
+                [1, -1].into_iter().map(move |delta| {
+                    let mut new_state = self.clone();
+                    for &(index, kind) in items {
+                        *new_state.pairs[index].floor_mut(kind) += delta;
+                    }
+                    new_state.elevator_floor += delta;
+                    (new_state, items)
+                })
+            })
+            .filter(move |(state, _)| state.is_valid(floor_count))
+    }
+}
 
-                if let Some(generators) = generators
-                    && !(generators.iter())
-                        .all(|&idx| self.pairs[idx].gen_floor == self.elevator_floor)
-                {
-                    return false;
-                }
+struct Step<'a> {
+    from: i8,
+    to: i8,
+    items: Vec<(&'a str, ItemKind)>,
+}
 
-                true
+impl Display for Step<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let items = (self.items.iter())
+            .map(|&(name, kind)| match kind {
+                ItemKind::Chip => format!("{name}-compatible microchip"),
+                ItemKind::Generator => format!("{name} generator"),
             })
-            .flat_map(move |(chips, generators)| {
-                let mut new_states = [self.clone(), self.clone()];
+            .join(", ");
 
-                if let Some(chips) = chips {
-                    for &chip_index in chips {
-                        new_states[0].pairs[chip_index].chip_floor += 1;
-                        new_states[1].pairs[chip_index].chip_floor -= 1;
-                    }
-                }
-                if let Some(generators) = generators {
-                    for &generator_index in generators {
-                        new_states[0].pairs[generator_index].gen_floor += 1;
-                        new_states[1].pairs[generator_index].gen_floor -= 1;
-                    }
-                }
-                new_states[0].pairs.sort_unstable();
-                new_states[1].pairs.sort_unstable();
+        write!(f, "floor {} -> {}: {items}", self.from + 1, self.to + 1)
+    }
+}
+
+struct Facility<'a> {
+    names: Vec<&'a str>,
+    floor_count: i8,
+    initial_state: State,
+}
 
-                new_states[0].elevator_floor += 1;
-                new_states[1].elevator_floor -= 1;
+impl<'a> Facility<'a> {
+    fn add_element(&mut self, name: &'a str, chip_floor: i8, gen_floor: i8) {
+        self.names.push(name);
+        self.initial_state
+            .pairs
+            .push(Pair::new(chip_floor, gen_floor));
+    }
 
-                new_states.into_iter().filter(|state| state.is_valid())
+    /// Compute all sets of at most `capacity` items which can be in the elevator together
+    fn possible_moves(&self, capacity: usize) -> Vec<Move> {
+        let items = (0..self.names.len())
+            .flat_map(|index| [(index, ItemKind::Chip), (index, ItemKind::Generator)])
+            .collect_vec();
+
+        (1..=capacity.min(items.len()))
+            .flat_map(|size| items.iter().copied().combinations(size))
+            .filter(|items| {
+                let has_generator = items.iter().any(|&(_, kind)| kind == ItemKind::Generator);
+
+                items.iter().all(|&(index, kind)| {
+                    kind == ItemKind::Generator
+                        || !has_generator
+                        || items.contains(&(index, ItemKind::Generator))
+                })
             })
+            .map(Move::from_vec)
+            .collect()
     }
-}
 
-fn solve(state: &State) -> usize {
-    // Possible moves: 1 chip or 1 generator or 2 chips or 2 generators or a pair of chip/generator of the same type
-    let possible_moves = (0..state.pairs.len())
-        .tuple_combinations()
-        .flat_map(|(x,)| {
-            [
-                (Some(smallvec![x]), None),
-                (None, Some(smallvec![x])),
-                (Some(smallvec![x]), Some(smallvec![x])),
-            ]
-        })
-        .chain(
-            (0..state.pairs.len())
-                .tuple_combinations()
-                .flat_map(|(x, y)| [(Some(smallvec![x, y]), None), (None, Some(smallvec![x, y]))]),
-        )
-        .collect_vec();
-
-    let mut current_states = vec![state.clone()];
-    let mut previous_states = HashSet::from([state.clone()]);
-    let mut next_states = Vec::new();
-
-    let mut steps = 0;
-    loop {
-        steps += 1;
-
-        for current_state in &current_states {
-            for next_state in current_state.next_states(&possible_moves) {
-                if next_state.is_final() {
-                    return steps;
+    /// Find a shortest sequence of elevator moves bringing all items to the top floor
+    fn solve(&self, capacity: usize) -> Result<Vec<Step<'a>>> {
+        let possible_moves = self.possible_moves(capacity);
+
+        let initial_state = self.initial_state.canonical();
+
+        let mut nodes = vec![(initial_state.clone(), 0)];
+        let mut previous_states = HashSet::from([initial_state]);
+        let mut current_indices = vec![0];
+        let mut next_indices = Vec::new();
+
+        let final_index = 'search: loop {
+            ensure!(!current_indices.is_empty(), "no solution found");
+
+            for &index in &current_indices {
+                if nodes[index].0.is_final(self.floor_count) {
+                    break 'search index;
                 }
 
-                if !previous_states.contains(&next_state) {
-                    previous_states.insert(next_state.clone());
-                    next_states.push(next_state);
+                let next_states = nodes[index]
+                    .0
+                    .next_states(&possible_moves, self.floor_count)
+                    .map(|(state, _)| state.canonical())
+                    .filter(|state| previous_states.insert(state.clone()))
+                    .collect_vec();
+
+                for state in next_states {
+                    next_indices.push(nodes.len());
+                    nodes.push((state, index));
                 }
             }
+
+            std::mem::swap(&mut current_indices, &mut next_indices);
+            next_indices.clear();
+        };
+
+        let mut canonical_path = vec![final_index];
+        while let Some(&index) = canonical_path.last().filter(|&&index| index != 0) {
+            canonical_path.push(nodes[index].1);
+        }
+
+        let mut state = self.initial_state.clone();
+        let mut steps = Vec::new();
+
+        for &index in canonical_path.iter().rev().skip(1) {
+            let (new_state, items) = state
+                .next_states(&possible_moves, self.floor_count)
+                .find(|(new_state, _)| new_state.canonical() == nodes[index].0)
+                .value()?;
+
+            steps.push(Step {
+                from: state.elevator_floor,
+                to: new_state.elevator_floor,
+                items: (items.iter())
+                    .map(|&(index, kind)| (self.names[index], kind))
+                    .collect(),
+            });
+
+            state = new_state;
         }
 
-        std::mem::swap(&mut current_states, &mut next_states);
-        next_states.clear();
+        Ok(steps)
     }
 }
 
-fn main() -> Result<()> {
-    let input = setup(file!())?;
-    let input = String::from_utf8_lossy(&input);
-
-    let regex_floor = Regex::new(r#"(\w+) floor"#)?;
+fn parse_facility(input: &str) -> Result<Facility<'_>> {
     let regex_gen_chip = Regex::new(r#"a (\w+)( generator|-compatible microchip)"#)?;
 
     let mut chips = HashMap::new();
     let mut generators = HashMap::new();
 
-    for line in input.lines() {
-        let floor: i8 = match &regex_floor.captures(line).value()?[1] {
-            "first" => 0,
-            "second" => 1,
-            "third" => 2,
-            "fourth" => 3,
-            other => bail!("unknown floor: {other}"),
-        };
-
+    for (floor, line) in input.lines().enumerate() {
         for cap in regex_gen_chip.captures_iter(line) {
             let element_type = cap.get(1).value()?.as_str();
             match &cap[2] {
-                "-compatible microchip" => chips.insert(element_type, floor),
-                " generator" => generators.insert(element_type, floor),
-                other => bail!("unknown element: {other}"),
+                "-compatible microchip" => chips.insert(element_type, floor as i8),
+                _ => generators.insert(element_type, floor as i8),
             };
         }
     }
 
-    let pairs = chips
-        .iter()
-        .map(|(&name, &chip_floor)| Pair::new(chip_floor, generators[name]))
-        .sorted_unstable()
+    ensure!(
+        chips.len() == generators.len() && chips.keys().all(|name| generators.contains_key(name)),
+        "each microchip should have a generator"
+    );
+
+    let names = chips.keys().copied().sorted_unstable().collect_vec();
+    let pairs = (names.iter())
+        .map(|name| Pair::new(chips[name], generators[name]))
         .collect();
 
-    let mut state = State::new(pairs, 0);
-    let result1 = solve(&state);
+    Ok(Facility {
+        names,
+        floor_count: input.lines().count().try_into()?,
+        initial_state: State::new(pairs, 0),
+    })
+}
+
+fn main() -> Result<()> {
+    let input = setup(file!())?;
+    let input = String::from_utf8_lossy(&input);
+
+    let capacity = option("--capacity")?.unwrap_or(ELEVATOR_CAPACITY);
+
+    let mut facility = parse_facility(&input)?;
+    let steps1 = facility.solve(capacity)?;
+
+    for name in EXTRA_ELEMENTS {
+        facility.add_element(name, 0, 0);
+    }
+    let steps2 = facility.solve(capacity)?;
+
+    if flag("--moves") {
+        for steps in [&steps1, &steps2] {
+            for (index, step) in steps.iter().enumerate() {
+                println!("{}. {step}", index + 1);
+            }
+            println!();
+        }
+    }
 
-    state.pairs.push(Pair::new(0, 0));
-    state.pairs.push(Pair::new(0, 0));
-    state.pairs.sort_unstable();
-    let result2 = solve(&state);
+    let result1 = steps1.len();
+    let result2 = steps2.len();
 
     println!("{result1}");
     println!("{result2}");
@@ -1,3 +1,4 @@
+use aoc::md5mine::PrefixHasher;
 use aoc::*;
 
 const UP: (u8, (i8, i8)) = (b'U', (0, -1));
@@ -17,7 +18,7 @@ fn main() -> Result<()> {
     let input = String::from_utf8_lossy(&input);
     let input = input.trim().as_bytes();
 
-    let mut buf = input.to_vec();
+    let hasher = PrefixHasher::new(input);
     let mut min_path = Option::<Vec<_>>::None;
     let mut max_path_len = 0;
     let mut states = vec![State::default()];
@@ -44,9 +45,7 @@ fn main() -> Result<()> {
             continue;
         }
 
-        buf.truncate(input.len());
-        buf.extend_from_slice(&state.path);
-        let hash = md5::compute(&buf);
+        let hash = hasher.hash(&state.path);
         let udlr_chars = [hash[0] >> 4, hash[0] & 0x0F, hash[1] >> 4, hash[1] & 0x0F];
 
         (udlr_chars.iter().zip(UDLR).filter(|&(&x, _)| x >= 11)).for_each(
@@ -1,3 +1,4 @@
+use aoc::dot::Dot;
 use aoc::*;
 
 use eyre::bail;
@@ -122,6 +123,25 @@ fn main() -> Result<()> {
 
     let total_weights = compute_total_weights(&nodes, &parents);
 
+    if flag("--dot") {
+        let mut dot = Dot::digraph();
+
+        for (name, node) in nodes.iter().sorted_unstable_by_key(|&(&name, _)| name) {
+            let label = format!(
+                "{name}\nweight {}\ntotal {}",
+                node.weight, total_weights[name]
+            );
+            dot.node(name, &[("label", &label)]);
+
+            for child_name in &node.children_names {
+                dot.edge(name, child_name, &[]);
+            }
+        }
+
+        print!("{dot}");
+        return Ok(());
+    }
+
     let result1 = bottom_node_name;
 
     let result2 =
@@ -1,131 +1,88 @@
+use aoc::dag::Dag;
+use aoc::dot::Dot;
+use aoc::schedule::{self, TieBreak};
 use aoc::*;
 
+use eyre::bail;
 use itertools::Itertools;
-use regex::bytes::Regex;
+use regex::Regex;
 
-use std::cmp::Reverse;
+use std::collections::HashMap;
 
-struct Node {
-    index: usize,
-    children: u64,
-    parents: u64,
+const WORKERS: usize = 5;
+const BASE_DURATION: u64 = 60;
+
+/// Duration of a step: the base duration plus the position of its letter in the alphabet
+fn duration(step: char, base_duration: u64) -> u64 {
+    base_duration + (step as u64 - 'A' as u64 + 1)
 }
 
-fn compute_step_list(nodes: &[Node]) -> String {
-    let mut step_list = String::new();
-    let mut visited_nodes = 0u64;
+fn main() -> Result<()> {
+    let input = setup(file!())?;
+    let input = String::from_utf8_lossy(&input);
 
-    let mut queue = nodes.iter().filter(|node| node.parents == 0).collect_vec();
-    queue.sort_unstable_by_key(|node| Reverse(node.index));
+    let re = Regex::new(r#"^Step ([A-Z]) must be finished before step ([A-Z]) can begin.$"#)?;
 
-    while let Some(node) = queue.pop() {
-        step_list.push((node.index as u8 + b'A') as char);
-        visited_nodes |= 1 << node.index;
+    let mut dependencies = HashMap::<char, Vec<char>>::new();
 
-        for child_node in nodes {
-            if (visited_nodes >> child_node.index) & 1 == 0
-                && (node.children >> child_node.index) & 1 != 0
-                && child_node.parents & visited_nodes == child_node.parents
-            {
-                queue.push(child_node);
-            }
-        }
+    for line in input.lines() {
+        let Some(cap) = re.captures(line) else {
+            bail!("invalid instruction: {line}");
+        };
 
-        queue.sort_unstable_by_key(|node| Reverse(node.index));
+        let before = cap[1].chars().next().value()?;
+        let after = cap[2].chars().next().value()?;
+
+        dependencies.entry(before).or_default();
+        dependencies.entry(after).or_default().push(before);
     }
 
-    step_list
-}
+    let mut steps = Dag::new();
+    for (&step, step_dependencies) in &dependencies {
+        steps.insert(step, (), step_dependencies.iter().copied());
+    }
 
-fn compute_total_time(nodes: &[Node]) -> u64 {
-    let mut total_time = 0u64;
-    let mut visited_nodes = 0u64;
-    let mut finished_nodes = 0u64;
-    let mut workers = [Option::<(&Node, u64)>::None; 5];
+    let worker_count = option("--workers")?.unwrap_or(WORKERS);
+    let base_duration = option("--base-duration")?.unwrap_or(BASE_DURATION);
 
-    let mut queue = nodes.iter().filter(|node| node.parents == 0).collect_vec();
-    for node in &queue {
-        visited_nodes |= 1 << node.index;
-    }
+    let tie_break = match option::<String>("--tie-break")? {
+        Some(name) => TieBreak::parse(&name)?,
+        None => TieBreak::Key,
+    };
 
-    loop {
-        queue.sort_unstable_by_key(|node| Reverse(node.index));
-
-        for worker in workers
-            .iter_mut()
-            .filter(|x| matches!(x, None | Some((_, 0))))
-        {
-            let Some(node) = queue.pop() else {
-                break;
-            };
-            *worker = Some((node, node.index as u64 + 61))
-        }
+    if flag("--dot") {
+        let mut dot = Dot::digraph();
 
-        let Some(elapsed_time) = workers
-            .iter()
-            .flatten()
-            .map(|&(_, remaining_time)| remaining_time)
-            .filter(|&remaining_time| remaining_time != 0)
-            .min()
-        else {
-            break;
-        };
+        for &step in dependencies.keys().sorted_unstable() {
+            let label = format!("{step}\n{}s", duration(step, base_duration));
+            dot.node(&step.to_string(), &[("label", &label)]);
+        }
 
-        total_time += elapsed_time;
-
-        for (worker_node, worker_timer) in workers
-            .iter_mut()
-            .flatten()
-            .filter(|(_, remaining_time)| *remaining_time != 0)
-        {
-            *worker_timer -= elapsed_time;
-
-            if *worker_timer == 0 {
-                finished_nodes |= 1 << worker_node.index;
-
-                for child_node in nodes {
-                    if (visited_nodes >> child_node.index) & 1 == 0
-                        && (worker_node.children >> child_node.index) & 1 != 0
-                        && child_node.parents & finished_nodes == child_node.parents
-                    {
-                        queue.push(child_node);
-                        visited_nodes |= 1 << child_node.index;
-                    }
-                }
+        for (&step, step_dependencies) in dependencies.iter().sorted_unstable() {
+            for dependency in step_dependencies {
+                dot.edge(&dependency.to_string(), &step.to_string(), &[]);
             }
         }
-    }
-
-    total_time
-}
 
-fn main() -> Result<()> {
-    let input = setup(file!())?;
-
-    let re = Regex::new(r#"(?m)^Step (\w) must be finished before step (\w) can begin.$"#)?;
-
-    let edges = re
-        .captures_iter(&input)
-        .map(|cap| [(cap[1][0] - b'A') as usize, (cap[2][0] - b'A') as usize])
-        .collect_vec();
+        print!("{dot}");
+        return Ok(());
+    }
 
-    let size = 1 + edges.iter().copied().flatten().max().value()?;
+    let order = schedule::schedule(&steps, 1, |_, _| 0, tie_break)?.order();
 
-    let mut nodes = (0..size)
-        .map(|index| Node {
-            index,
-            children: 0,
-            parents: 0,
-        })
-        .collect_vec();
+    let timeline = schedule::schedule(
+        &steps,
+        worker_count,
+        |step, _| duration(step, base_duration),
+        tie_break,
+    )?;
 
-    for &edge in &edges {
-        nodes[edge[0]].children |= 1 << edge[1];
-        nodes[edge[1]].parents |= 1 << edge[0];
+    if flag("--gantt") {
+        println!("{}", timeline.render(|step| step.to_string()));
     }
 
-    let result1 = compute_step_list(&nodes);
-    let result2 = compute_total_time(&nodes);
+    let result1 = order.iter().collect::<String>();
+    let result2 = timeline.makespan();
 
     println!("{result1}");
     println!("{result2}");
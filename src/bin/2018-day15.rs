@@ -1,11 +1,14 @@
 use aoc::*;
 
-use eyre::{bail, ensure};
+use eyre::{bail, ensure, eyre};
+use itertools::Itertools;
 use smallvec::SmallVec;
 
 use std::cmp::Ordering;
 use std::collections::{BinaryHeap, HashSet};
-use std::ops::ControlFlow;
+use std::fmt::{self, Display};
+use std::iter;
+use std::ops::{ControlFlow, RangeInclusive};
 
 const MAX_HP: i64 = 200;
 const BASE_ATTACK_POWER: i64 = 3;
@@ -79,6 +82,8 @@ enum FighterId {
 trait ICreature {
     type EnemyType: ICreature;
 
+    const SYMBOL: u8;
+
     fn tile_index(&self) -> usize;
     fn tile_index_mut(&mut self) -> &mut usize;
     fn hp(&self) -> i64;
@@ -86,8 +91,205 @@ trait ICreature {
     fn enemy_id(tile: &Tile) -> Option<usize>;
 }
 
+/// Identifier of a unit, displayed as its symbol followed by its index (e.g. `E3`)
+#[derive(Copy, Clone)]
+struct UnitId {
+    symbol: u8,
+    id: usize,
+}
+
+impl Display for UnitId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.symbol as char, self.id)
+    }
+}
+
+enum Event {
+    Move {
+        unit: UnitId,
+        from: Position,
+        to: Position,
+    },
+    Attack {
+        unit: UnitId,
+        target: UnitId,
+        damage: i64,
+        hp: i64,
+    },
+    Death {
+        unit: UnitId,
+    },
+}
+
+impl Event {
+    fn to_json(&self, round: usize) -> String {
+        match *self {
+            Event::Move { unit, from, to } => format!(
+                r#"{{"round":{round},"event":"move","unit":"{unit}","from":[{},{}],"to":[{},{}]}}"#,
+                from.0, from.1, to.0, to.1
+            ),
+            Event::Attack {
+                unit,
+                target,
+                damage,
+                hp,
+            } => format!(
+                r#"{{"round":{round},"event":"attack","unit":"{unit}","target":"{target}","damage":{damage},"hp":{hp}}}"#
+            ),
+            Event::Death { unit } => {
+                format!(r#"{{"round":{round},"event":"death","unit":"{unit}"}}"#)
+            }
+        }
+    }
+}
+
+impl Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Event::Move { unit, from, to } => write!(f, "{unit} moves from {from:?} to {to:?}"),
+            Event::Attack {
+                unit,
+                target,
+                damage,
+                hp,
+            } => write!(
+                f,
+                "{unit} attacks {target} for {damage} damage ({hp} HP left)"
+            ),
+            Event::Death { unit } => write!(f, "{unit} dies"),
+        }
+    }
+}
+
+/// Map and units HP of each row at a given time of the battle
+struct Frame {
+    rows: Vec<Vec<u8>>,
+    units: Vec<Vec<(u8, i64)>>,
+}
+
+impl Frame {
+    fn new(grid: &Grid, elfs: &[Option<Elf>], goblins: &[Option<Goblin>]) -> Self {
+        let mut rows = vec![Vec::with_capacity(grid.width); grid.height];
+        let mut units = vec![Vec::new(); grid.height];
+
+        for (index, tile) in grid.tiles.iter().enumerate() {
+            let (row, _) = grid.get_position(index);
+
+            let (symbol, hp) = match *tile {
+                Tile::Empty => (b'.', None),
+                Tile::Wall => (b'#', None),
+                Tile::Elf(id) => (b'E', elfs[id].as_ref().map(|elf| elf.hp)),
+                Tile::Goblin(id) => (b'G', goblins[id].as_ref().map(|goblin| goblin.hp)),
+            };
+
+            rows[row].push(symbol);
+            units[row].extend(hp.map(|hp| (symbol, hp)));
+        }
+
+        Self { rows, units }
+    }
+
+    /// Render the map with ANSI colors, with the HP of the units of each row on its right
+    fn render(&self) -> String {
+        let color = |symbol: u8| match symbol {
+            b'E' => "\x1b[1;32m",
+            b'G' => "\x1b[1;31m",
+            b'#' => "\x1b[90m",
+            _ => "",
+        };
+
+        let mut output = String::new();
+
+        for (row, units) in iter::zip(&self.rows, &self.units) {
+            for &symbol in row {
+                match color(symbol) {
+                    "" => output.push(symbol as char),
+                    color => output += &format!("{color}{}\x1b[0m", symbol as char),
+                }
+            }
+
+            if !units.is_empty() {
+                let sidebar = units
+                    .iter()
+                    .map(|&(symbol, hp)| {
+                        format!("{}{}({hp})\x1b[0m", color(symbol), symbol as char)
+                    })
+                    .join(", ");
+
+                output += "   ";
+                output += &sidebar;
+            }
+
+            output.push('\n');
+        }
+
+        output
+    }
+}
+
+/// Recording of all the events and intermediate states of a battle
+#[derive(Default)]
+struct Replay {
+    enabled: bool,
+    rounds: Vec<Vec<Event>>,
+    frames: Vec<Frame>,
+}
+
+impl Replay {
+    fn push_event(&mut self, event: impl FnOnce() -> Event) {
+        if let Some(events) = self.rounds.last_mut() {
+            events.push(event());
+        }
+    }
+
+    fn start_round(&mut self) {
+        if self.enabled {
+            self.rounds.push(Vec::new());
+        }
+    }
+
+    fn push_frame(&mut self, grid: &Grid, elfs: &[Option<Elf>], goblins: &[Option<Goblin>]) {
+        if self.enabled {
+            self.frames.push(Frame::new(grid, elfs, goblins));
+        }
+    }
+
+    /// Render the events of each round followed by the map at the end of the round
+    fn render(&self) -> String {
+        let mut output = String::new();
+
+        for (round, frame) in self.frames.iter().enumerate() {
+            match round {
+                0 => output += "Initially:\n",
+                _ => {
+                    for event in &self.rounds[round - 1] {
+                        output += &format!("  {event}\n");
+                    }
+
+                    match round {
+                        _ if round == self.rounds.len() => output += "Combat ends:\n",
+                        1 => output += "After 1 round:\n",
+                        _ => output += &format!("After {round} rounds:\n"),
+                    }
+                }
+            }
+
+            output += &frame.render();
+            output.push('\n');
+        }
+
+        output
+    }
+
+    fn to_json_lines(&self) -> String {
+        (self.rounds.iter().enumerate())
+            .flat_map(|(round, events)| events.iter().map(move |event| event.to_json(round + 1)))
+            .join("\n")
+    }
+}
+
 macro_rules! new_creature {
-    ($type_name:ident, $attack_power_field:ident, $enemy_tile_path:path, $enemy_name:ident) => {
+    ($type_name:ident, $symbol:literal, $enemy_tile_path:path, $enemy_name:ident) => {
         #[derive(Clone)]
         struct $type_name {
             tile_index: usize,
@@ -97,6 +299,8 @@ macro_rules! new_creature {
         impl ICreature for $type_name {
             type EnemyType = $enemy_name;
 
+            const SYMBOL: u8 = $symbol;
+
             fn tile_index(&self) -> usize {
                 self.tile_index
             }
@@ -119,13 +323,14 @@ macro_rules! new_creature {
     };
 }
 
-new_creature!(Elf, elf_attack_power, Tile::Goblin, Goblin);
-new_creature!(Goblin, goblin_attack_power, Tile::Elf, Elf);
+new_creature!(Elf, b'E', Tile::Goblin, Goblin);
+new_creature!(Goblin, b'G', Tile::Elf, Elf);
 
 #[derive(Clone)]
 struct Battle {
     elf_attack_power: i64,
     goblin_attack_power: i64,
+    abort_on_elf_death: bool,
     grid: Grid,
     fighter_ids: Vec<FighterId>,
     elfs: Vec<Option<Elf>>,
@@ -304,36 +509,61 @@ fn compute_attack_target_id<Creature: ICreature>(
 }
 
 fn attack<Creature: ICreature>(
-    enemy: &mut Option<<Creature as ICreature>::EnemyType>,
+    unit: UnitId,
+    enemy_id: usize,
+    enemies: &mut [Option<Creature::EnemyType>],
     attack_power: i64,
     grid: &mut Grid,
     casualties: &mut bool,
+    replay: &mut Replay,
 ) -> Result<()> {
+    let enemy = &mut enemies[enemy_id];
     let enemy_creature = enemy.as_mut().value()?;
     *enemy_creature.hp_mut() -= attack_power;
 
+    let target = UnitId {
+        symbol: <Creature::EnemyType as ICreature>::SYMBOL,
+        id: enemy_id,
+    };
+
+    replay.push_event(|| Event::Attack {
+        unit,
+        target,
+        damage: attack_power,
+        hp: enemy_creature.hp().max(0),
+    });
+
     if enemy_creature.hp() <= 0 {
         grid.tiles[enemy_creature.tile_index()] = Tile::Empty;
         *casualties = true;
         *enemy = None;
+        replay.push_event(|| Event::Death { unit: target });
     }
 
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 fn take_turn<Creature: ICreature>(
+    id: usize,
     creature: Option<&mut Creature>,
     attack_power: i64,
     enemies: &mut [Option<Creature::EnemyType>],
     grid: &mut Grid,
     casualties: &mut bool,
     buffer: &mut Buffer,
+    replay: &mut Replay,
 ) -> Result<ControlFlow<()>> {
     let creature = match creature {
         Some(creature) => creature,
         None => return Ok(ControlFlow::Continue(())),
     };
 
+    let unit = UnitId {
+        symbol: Creature::SYMBOL,
+        id,
+    };
+
     if enemies.iter().flatten().next().is_none() {
         return Ok(ControlFlow::Break(()));
     }
@@ -342,9 +572,15 @@ fn take_turn<Creature: ICreature>(
     let adjacent_tile_indices = grid.adjacent_tile_indices(row, column);
 
     match compute_attack_target_id::<Creature>(&adjacent_tile_indices, enemies, grid) {
-        Some(enemy_id) => {
-            attack::<Creature>(&mut enemies[enemy_id], attack_power, grid, casualties)?
-        }
+        Some(enemy_id) => attack::<Creature>(
+            unit,
+            enemy_id,
+            enemies,
+            attack_power,
+            grid,
+            casualties,
+            replay,
+        )?,
         None => {
             let (new_tile_index, in_range) =
                 match movement::<Creature>(&adjacent_tile_indices, enemies, grid, buffer) {
@@ -356,6 +592,12 @@ fn take_turn<Creature: ICreature>(
             *creature.tile_index_mut() = new_tile_index;
             grid.tiles.swap(old_tile_index, new_tile_index);
 
+            replay.push_event(|| Event::Move {
+                unit,
+                from: grid.get_position(old_tile_index),
+                to: grid.get_position(new_tile_index),
+            });
+
             if in_range {
                 let (row, column) = grid.get_position(creature.tile_index());
 
@@ -366,7 +608,15 @@ fn take_turn<Creature: ICreature>(
                 )
                 .value()?;
 
-                attack::<Creature>(&mut enemies[enemy_id], attack_power, grid, casualties)?;
+                attack::<Creature>(
+                    unit,
+                    enemy_id,
+                    enemies,
+                    attack_power,
+                    grid,
+                    casualties,
+                    replay,
+                )?;
             }
         }
     }
@@ -374,10 +624,11 @@ fn take_turn<Creature: ICreature>(
     Ok(ControlFlow::Continue(()))
 }
 
-fn run(battle: Battle, buffer: &mut Buffer) -> Result<(i64, bool)> {
+fn run(battle: Battle, buffer: &mut Buffer, replay: &mut Replay) -> Result<(i64, bool)> {
     let Battle {
         elf_attack_power,
         goblin_attack_power,
+        abort_on_elf_death,
         mut grid,
         mut fighter_ids,
         mut elfs,
@@ -386,32 +637,45 @@ fn run(battle: Battle, buffer: &mut Buffer) -> Result<(i64, bool)> {
 
     let mut turns = 0;
 
+    replay.push_frame(&grid, &elfs, &goblins);
+
     'run: loop {
         let mut casualties = false;
 
+        replay.start_round();
+
         for fighter_id in &fighter_ids {
             let action = match *fighter_id {
                 FighterId::Elf(id) => take_turn(
+                    id,
                     elfs[id].as_mut(),
                     elf_attack_power,
                     &mut goblins,
                     &mut grid,
                     &mut casualties,
                     buffer,
+                    replay,
                 )?,
                 FighterId::Goblin(id) => take_turn(
+                    id,
                     goblins[id].as_mut(),
                     goblin_attack_power,
                     &mut elfs,
                     &mut grid,
                     &mut casualties,
                     buffer,
+                    replay,
                 )?,
             };
 
             if action == ControlFlow::Break(()) {
+                replay.push_frame(&grid, &elfs, &goblins);
                 break 'run;
             }
+
+            if abort_on_elf_death && casualties && elfs.iter().any(|elf| elf.is_none()) {
+                return Ok((0, true));
+            }
         }
 
         if casualties {
@@ -426,6 +690,8 @@ fn run(battle: Battle, buffer: &mut Buffer) -> Result<(i64, bool)> {
             FighterId::Goblin(id) => goblins[id].as_ref().map(|x| x.tile_index),
         });
 
+        replay.push_frame(&grid, &elfs, &goblins);
+
         turns += 1;
     }
 
@@ -441,6 +707,59 @@ fn run(battle: Battle, buffer: &mut Buffer) -> Result<(i64, bool)> {
     Ok((turns * hp_sum, elf_casualties))
 }
 
+/// Range of attack powers killing a goblin in the same number of hits, which all lead to the same battle
+struct AttackPowerClass {
+    hits: i64,
+    attack_powers: RangeInclusive<i64>,
+}
+
+fn compute_attack_power_classes() -> Vec<AttackPowerClass> {
+    (BASE_ATTACK_POWER + 1..=MAX_HP)
+        .chunk_by(|attack_power| (MAX_HP + attack_power - 1) / attack_power)
+        .into_iter()
+        .flat_map(|(hits, mut attack_powers)| {
+            let first = attack_powers.next()?;
+            let last = attack_powers.last().unwrap_or(first);
+            Some(AttackPowerClass {
+                hits,
+                attack_powers: first..=last,
+            })
+        })
+        .collect()
+}
+
+/// Binary search the smallest attack power for which no elf dies, assuming that higher attack powers are always better for elves.
+///
+/// Only one attack power per class is simulated, and each simulation stops as soon as an elf dies.
+fn find_minimal_attack_power(
+    initial_battle: &Battle,
+    classes: &[AttackPowerClass],
+    buffer: &mut Buffer,
+) -> Result<(usize, i64)> {
+    let (mut low, mut high) = (0, classes.len());
+    let mut best = None;
+
+    while low < high {
+        let middle = (low + high) / 2;
+
+        let battle = Battle {
+            elf_attack_power: *classes[middle].attack_powers.start(),
+            abort_on_elf_death: true,
+            ..initial_battle.clone()
+        };
+
+        match run(battle, buffer, &mut Replay::default())? {
+            (_, true) => low = middle + 1,
+            (outcome, false) => {
+                best = Some((middle, outcome));
+                high = middle;
+            }
+        }
+    }
+
+    best.ok_or_else(|| eyre!("elves cannot win without casualties"))
+}
+
 fn parse_initial_battle(input: &str) -> Result<Battle> {
     let width = input.lines().map(|line| line.len()).max().value()?;
     let height = input.lines().count();
@@ -485,6 +804,7 @@ fn parse_initial_battle(input: &str) -> Result<Battle> {
     Ok(Battle {
         elf_attack_power: BASE_ATTACK_POWER,
         goblin_attack_power: BASE_ATTACK_POWER,
+        abort_on_elf_death: false,
         grid,
         fighter_ids,
         elfs,
@@ -500,24 +820,40 @@ fn main() -> Result<()> {
 
     let mut buffer = Buffer::default();
 
-    let (result1, elf_casualties) = run(initial_battle.clone(), &mut buffer)?;
+    let mut replay = Replay {
+        enabled: flag("--replay") || flag("--replay-json"),
+        ..Replay::default()
+    };
+
+    let (result1, elf_casualties) = run(initial_battle.clone(), &mut buffer, &mut replay)?;
+
+    if flag("--replay") {
+        print!("{}", replay.render());
+    } else if flag("--replay-json") {
+        println!("{}", replay.to_json_lines());
+    }
 
     let result2 = match elf_casualties {
         false => result1,
-        true => (BASE_ATTACK_POWER + 1..)
-            .find_map(|elf_attack_power| {
-                (|| {
-                    let battle = Battle {
-                        elf_attack_power,
-                        ..initial_battle.clone()
+        true => {
+            let classes = compute_attack_power_classes();
+            let (class_index, outcome) =
+                find_minimal_attack_power(&initial_battle, &classes, &mut buffer)?;
+
+            if flag("--attack-powers") {
+                for (index, class) in classes.iter().enumerate() {
+                    let (start, end) = (class.attack_powers.start(), class.attack_powers.end());
+                    let marker = if index == class_index {
+                        " (minimal)"
+                    } else {
+                        ""
                     };
-                    let (outcome, elf_casualties) = run(battle, &mut buffer)?;
-                    Result::Ok((!elf_casualties).then_some(outcome))
-                })()
-                .transpose()
-            })
-            .transpose()?
-            .value()?,
+                    println!("attack power {start}..={end}: {} hits{marker}", class.hits);
+                }
+            }
+
+            outcome
+        }
     };
 
     println!("{result1}");
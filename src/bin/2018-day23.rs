@@ -1,3 +1,4 @@
+use aoc::boxes::Box;
 use aoc::*;
 
 use itertools::Itertools;
@@ -5,18 +6,6 @@ use regex::Regex;
 
 use std::cmp::{Ordering, Reverse};
 use std::collections::BinaryHeap;
-use std::iter;
-
-const OCTANTS: [[i64; 3]; 8] = [
-    [0, 0, 0],
-    [0, 0, 1],
-    [0, 1, 0],
-    [0, 1, 1],
-    [1, 0, 0],
-    [1, 0, 1],
-    [1, 1, 0],
-    [1, 1, 1],
-];
 
 struct Nanobot {
     position: [i64; 3],
@@ -25,26 +14,31 @@ struct Nanobot {
 
 struct State {
     intersecting_bots: usize,
-    box_corner: [i64; 3],
-    box_size: i64,
-    box_corner_distance: i64,
+    region: Box<3>,
+    distance: i64,
 }
 
 impl State {
-    fn new(intersecting_bots: usize, box_corner: [i64; 3], box_size: i64) -> Self {
+    fn new(region: Box<3>, nanobots: &[Nanobot]) -> Self {
         Self {
-            intersecting_bots,
-            box_corner,
-            box_size,
-            box_corner_distance: box_corner.into_iter().map(i64::abs).sum(),
+            intersecting_bots: nanobots
+                .iter()
+                .filter(|nanobot| region.distance_to(nanobot.position) <= nanobot.radius)
+                .count(),
+            region,
+            distance: region.distance_to([0; 3]),
         }
     }
 
+    fn size(&self) -> i64 {
+        self.region.end[0] - self.region.start[0]
+    }
+
     fn estimate(&self) -> (Reverse<usize>, Reverse<i64>, i64) {
         (
             Reverse(self.intersecting_bots),
-            Reverse(self.box_size),
-            self.box_corner_distance,
+            Reverse(self.size()),
+            self.distance,
         )
     }
 }
@@ -69,24 +63,6 @@ impl PartialOrd for State {
     }
 }
 
-fn intersecting_bots(box_corner: [i64; 3], box_size: i64, nanobots: &[Nanobot]) -> usize {
-    nanobots
-        .iter()
-        .filter(|&nanobot| {
-            let box_distance = iter::zip(nanobot.position, box_corner)
-                .map(|(p, c)| {
-                    let box_low = c;
-                    let box_high = c + box_size - 1;
-
-                    (box_low - p).max(0) + (p - box_high).max(0)
-                })
-                .sum::<i64>();
-
-            box_distance <= nanobot.radius
-        })
-        .count()
-}
-
 fn main() -> Result<()> {
     let input = setup(file!())?;
     let input = String::from_utf8_lossy(&input);
@@ -133,43 +109,18 @@ fn main() -> Result<()> {
 
     let max_box_size = i64::try_from(bounding_box.next_power_of_two())?;
 
-    let initial_intersecting_bots = nanobots.len();
-    let initial_box_corner = [-max_box_size; 3];
-    let initial_box_size = 2 * max_box_size;
+    let initial_region = Box::new([-max_box_size; 3], [max_box_size; 3]);
+    let mut current_states = BinaryHeap::from([State::new(initial_region, &nanobots)]);
 
-    let initial_state = State::new(
-        initial_intersecting_bots,
-        initial_box_corner,
-        initial_box_size,
-    );
+    let result2 = loop {
+        let state = current_states.pop().value()?;
 
-    let mut current_states = BinaryHeap::from([initial_state]);
+        if state.size() == 1 {
+            break state.distance;
+        }
 
-    let result2 = loop {
-        if let Some(state) = current_states.pop() {
-            if state.box_size == 1 {
-                break state.box_corner_distance;
-            }
-
-            let new_box_size = state.box_size / 2;
-
-            for octant in OCTANTS {
-                let mut new_box_corner = state.box_corner;
-
-                new_box_corner
-                    .iter_mut()
-                    .zip(octant)
-                    .for_each(|(x, o)| *x += new_box_size * o);
-
-                let new_intersecting_bots =
-                    intersecting_bots(new_box_corner, new_box_size, &nanobots);
-
-                current_states.push(State::new(
-                    new_intersecting_bots,
-                    new_box_corner,
-                    new_box_size,
-                ));
-            }
+        for region in state.region.split() {
+            current_states.push(State::new(region, &nanobots));
         }
     };
 
@@ -5,6 +5,7 @@ use itertools::Itertools;
 use regex::Regex;
 
 use std::cmp::Reverse;
+use std::fmt::{self, Display};
 use std::iter;
 
 #[derive(Clone)]
@@ -19,12 +20,83 @@ struct Group<'a> {
     effective_power: i64,
 }
 
-#[derive(Clone)]
+#[derive(Copy, Clone)]
 enum GroupId {
     ImmuneSystem(usize),
     Infection(usize),
 }
 
+impl Display for GroupId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            GroupId::ImmuneSystem(index) => write!(f, "immune system group {}", index + 1),
+            GroupId::Infection(index) => write!(f, "infection group {}", index + 1),
+        }
+    }
+}
+
+enum Outcome {
+    ImmuneSystemWins(i64),
+    InfectionWins(i64),
+    Stalemate,
+}
+
+impl Outcome {
+    fn is_immune_system_win(&self) -> bool {
+        matches!(self, Outcome::ImmuneSystemWins(_))
+    }
+}
+
+impl Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::ImmuneSystemWins(units) => write!(f, "immune system wins with {units} units"),
+            Outcome::InfectionWins(units) => write!(f, "infection wins with {units} units"),
+            Outcome::Stalemate => write!(f, "stalemate"),
+        }
+    }
+}
+
+struct AttackReport {
+    attacker: GroupId,
+    target: GroupId,
+    units_killed: i64,
+}
+
+/// Attacks of each round, recorded only when enabled
+#[derive(Default)]
+struct CasualtyReport {
+    enabled: bool,
+    rounds: Vec<Vec<AttackReport>>,
+}
+
+impl Display for CasualtyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (round, attacks) in self.rounds.iter().enumerate() {
+            writeln!(f, "round {}:", round + 1)?;
+
+            if attacks.is_empty() {
+                writeln!(f, "  no attacks")?;
+            }
+
+            for attack in attacks {
+                let stalled = if attack.units_killed == 0 {
+                    " (stalled)"
+                } else {
+                    ""
+                };
+
+                writeln!(
+                    f,
+                    "  {} attacks {}, killing {} units{stalled}",
+                    attack.attacker, attack.target, attack.units_killed
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(Clone)]
 struct Battle<'a> {
     immune_system: Vec<Option<Group<'a>>>,
@@ -89,11 +161,17 @@ fn compute_target_selection(
 fn attack(
     army: &[Option<Group>],
     enemy_army: &mut [Option<Group>],
-    group_index: usize,
+    group_id: GroupId,
     attacks: &[Option<(usize, i64)>],
     boost: i64,
     locked: &mut bool,
+    report: &mut CasualtyReport,
 ) {
+    let (group_index, enemy_group_id): (_, fn(usize) -> GroupId) = match group_id {
+        GroupId::ImmuneSystem(index) => (index, GroupId::Infection),
+        GroupId::Infection(index) => (index, GroupId::ImmuneSystem),
+    };
+
     if let (Some(group), Some((enemy_index, attack_factor))) =
         (army[group_index].as_ref(), attacks[group_index])
         && let Some(ref mut enemy_group) = enemy_army[enemy_index]
@@ -101,6 +179,14 @@ fn attack(
         let casualties =
             attack_factor * group.unit_count * (group.attack_damage + boost) / enemy_group.unit_hp;
 
+        if let Some(attacks) = report.rounds.last_mut() {
+            attacks.push(AttackReport {
+                attacker: group_id,
+                target: enemy_group_id(enemy_index),
+                units_killed: casualties.min(enemy_group.unit_count),
+            });
+        }
+
         enemy_group.unit_count -= casualties;
 
         if casualties > 0 {
@@ -113,7 +199,12 @@ fn attack(
     }
 }
 
-fn run(mut battle: Battle, buffer: &mut Buffer, boost: i64) -> std::result::Result<i64, i64> {
+fn run(
+    mut battle: Battle,
+    buffer: &mut Buffer,
+    boost: i64,
+    report: &mut CasualtyReport,
+) -> Outcome {
     battle
         .immune_system
         .iter_mut()
@@ -161,33 +252,39 @@ fn run(mut battle: Battle, buffer: &mut Buffer, boost: i64) -> std::result::Resu
 
         let mut locked = true;
 
-        for group_id in &battle.attack_order {
-            match *group_id {
-                GroupId::ImmuneSystem(group_index) => {
+        if report.enabled {
+            report.rounds.push(Vec::new());
+        }
+
+        for &group_id in &battle.attack_order {
+            match group_id {
+                GroupId::ImmuneSystem(_) => {
                     attack(
                         &battle.immune_system,
                         &mut battle.infection,
-                        group_index,
+                        group_id,
                         &buffer.immune_system_attacks,
                         boost,
                         &mut locked,
+                        report,
                     );
                 }
-                GroupId::Infection(group_index) => {
+                GroupId::Infection(_) => {
                     attack(
                         &battle.infection,
                         &mut battle.immune_system,
-                        group_index,
+                        group_id,
                         &buffer.infection_attacks,
                         0,
                         &mut locked,
+                        report,
                     );
                 }
             }
         }
 
         if locked {
-            break Err(0);
+            break Outcome::Stalemate;
         }
 
         let iter =
@@ -196,12 +293,14 @@ fn run(mut battle: Battle, buffer: &mut Buffer, boost: i64) -> std::result::Resu
             });
 
         if iter.count() == 0 {
-            break Err(battle
-                .infection
-                .iter_mut()
-                .flatten()
-                .map(|group| group.unit_count)
-                .sum::<i64>());
+            break Outcome::InfectionWins(
+                battle
+                    .infection
+                    .iter_mut()
+                    .flatten()
+                    .map(|group| group.unit_count)
+                    .sum::<i64>(),
+            );
         }
 
         let iter = battle
@@ -211,16 +310,85 @@ fn run(mut battle: Battle, buffer: &mut Buffer, boost: i64) -> std::result::Resu
             .map(|group| group.effective_power = group.unit_count * group.attack_damage);
 
         if iter.count() == 0 {
-            break Ok(battle
-                .immune_system
-                .iter_mut()
-                .flatten()
-                .map(|group| group.unit_count)
-                .sum::<i64>());
+            break Outcome::ImmuneSystemWins(
+                battle
+                    .immune_system
+                    .iter_mut()
+                    .flatten()
+                    .map(|group| group.unit_count)
+                    .sum::<i64>(),
+            );
         }
     }
 }
 
+/// Outcomes of the battles simulated while searching for the smallest winning boost
+struct BoostAnalysis {
+    probes: Vec<(i64, Outcome)>,
+    minimal_boost: i64,
+}
+
+impl BoostAnalysis {
+    /// Find the smallest boost for which the immune system wins, assuming that higher boosts are always better for the immune system.
+    ///
+    /// An upper bound is found by doubling the boost, then the boost is bisected.
+    /// Stalemates are counted as losses for the immune system.
+    fn new(initial_battle: &Battle, buffer: &mut Buffer) -> Result<Self> {
+        let mut probes = Vec::new();
+
+        let mut is_win = |boost| {
+            let outcome = run(
+                initial_battle.clone(),
+                buffer,
+                boost,
+                &mut CasualtyReport::default(),
+            );
+            let is_win = outcome.is_immune_system_win();
+            probes.push((boost, outcome));
+            is_win
+        };
+
+        if is_win(0) {
+            return Ok(Self {
+                probes,
+                minimal_boost: 0,
+            });
+        }
+
+        let (mut low, mut high) = (0, 1);
+
+        while !is_win(high) {
+            low = high;
+            high = high.checked_mul(2).value()?;
+        }
+
+        while high - low > 1 {
+            let middle = low + (high - low) / 2;
+            match is_win(middle) {
+                true => high = middle,
+                false => low = middle,
+            }
+        }
+
+        probes.sort_unstable_by_key(|&(boost, _)| boost);
+
+        Ok(Self {
+            probes,
+            minimal_boost: high,
+        })
+    }
+
+    fn outcome(&self, boost: i64) -> Option<&Outcome> {
+        (self.probes.iter())
+            .find_map(|(probe_boost, outcome)| (*probe_boost == boost).then_some(outcome))
+    }
+
+    fn stalemates(&self) -> impl Iterator<Item = i64> {
+        (self.probes.iter())
+            .filter_map(|(boost, outcome)| matches!(outcome, Outcome::Stalemate).then_some(*boost))
+    }
+}
+
 fn parse_initial_battle(input: &str) -> Result<Battle<'_>> {
     let regex_armies = Regex::new(r#"(?ms)^Immune System:$(.*)^Infection:$(.*)$"#)?;
     let regex_units = Regex::new(
@@ -312,14 +480,38 @@ fn main() -> Result<()> {
         infection_attacks: vec![None; initial_battle.infection.len()],
     };
 
-    let mut iter = (0..).map(|boost| run(initial_battle.clone(), &mut buffer, boost));
+    let analysis = BoostAnalysis::new(&initial_battle, &mut buffer)?;
+
+    if flag("--boosts") {
+        for (boost, outcome) in &analysis.probes {
+            println!("boost {boost}: {outcome}");
+        }
 
-    let (result1, result2) = match iter.next().value()? {
-        Ok(result) => (result, result),
-        Err(result1) => {
-            let result2 = iter.find_map(|x| x.ok()).value()?;
-            (result1, result2)
+        let stalemates = analysis.stalemates().join(", ");
+        if !stalemates.is_empty() {
+            println!("stalemates with boosts: {stalemates}");
         }
+    }
+
+    if let Some(boost) = option("--report")? {
+        let mut report = CasualtyReport {
+            enabled: true,
+            ..CasualtyReport::default()
+        };
+
+        let outcome = run(initial_battle.clone(), &mut buffer, boost, &mut report);
+        print!("{report}");
+        println!("boost {boost}: {outcome}");
+    }
+
+    let result1 = match analysis.outcome(0).value()? {
+        Outcome::ImmuneSystemWins(units) | Outcome::InfectionWins(units) => *units,
+        Outcome::Stalemate => bail!("stalemate without boost"),
+    };
+
+    let result2 = match analysis.outcome(analysis.minimal_boost).value()? {
+        Outcome::ImmuneSystemWins(units) => *units,
+        _ => bail!("immune system should win"),
     };
 
     println!("{result1}");
@@ -1,3 +1,4 @@
+use aoc::dot::Dot;
 use aoc::*;
 
 use itertools::Itertools;
@@ -33,6 +34,25 @@ fn main() -> Result<()> {
         inverted_graph.insert(object, center);
     }
 
+    if flag("--dot") {
+        let mut dot = Dot::digraph();
+
+        for name in ["COM", "YOU", "SAN"] {
+            if graph.contains_key(name) {
+                dot.node(name, &[("style", "filled"), ("fillcolor", "gold")]);
+            }
+        }
+
+        for (center, objects) in graph.iter().sorted_unstable_by_key(|&(&center, _)| center) {
+            for object in objects {
+                dot.edge(center, object, &[]);
+            }
+        }
+
+        print!("{dot}");
+        return Ok(());
+    }
+
     let mut orbit_count = 0usize;
     let mut queue = vec![(0, "COM")];
 
@@ -1,125 +1,241 @@
 use aoc::*;
 
-use eyre::bail;
+use eyre::{bail, ensure};
 use itertools::Itertools;
 
+const SMALL_SIZE: i128 = 10007;
 const BIG_SIZE: i128 = 119315717514047;
+const REPETITIONS: i128 = 101741582076661;
+
+/// Examples of the puzzle, with the resulting 10-card decks
+const EXAMPLES: [(&str, [i128; 10]); 4] = [
+    (
+        "deal with increment 7\ndeal into new stack\ndeal into new stack",
+        [0, 3, 6, 9, 2, 5, 8, 1, 4, 7],
+    ),
+    (
+        "cut 6\ndeal with increment 7\ndeal into new stack",
+        [3, 0, 7, 4, 1, 8, 5, 2, 9, 6],
+    ),
+    (
+        "deal with increment 7\ndeal with increment 9\ncut -2",
+        [6, 3, 0, 7, 4, 1, 8, 5, 2, 9],
+    ),
+    (
+        concat!(
+            "deal into new stack\ncut -2\ndeal with increment 7\ncut 8\ncut -4\n",
+            "deal with increment 7\ncut 3\ndeal with increment 9\ndeal with increment 3\ncut -1"
+        ),
+        [9, 2, 5, 8, 1, 4, 7, 0, 3, 6],
+    ),
+];
+
+/// Compute the inverse of `x` modulo `m` with the extended Euclidean algorithm
+fn mod_inverse(x: i128, m: i128) -> Option<i128> {
+    let (mut r1, mut u1, mut r2, mut u2) = (x.rem_euclid(m), 1, m, 0);
+
+    while r2 != 0 {
+        let q = r1 / r2;
+        (r1, u1, r2, u2) = (r2, u2, r1 - q * r2, u1 - q * u2);
+    }
 
-trait Modulo: Sized {
-    fn inv(self) -> Self;
-    fn sub(self, other: Self) -> Self;
-    fn add(self, other: Self) -> Self;
-    fn mul(self, other: Self) -> Self;
-    fn power(self, other: Self) -> Self;
+    (r1 == 1).then(|| u1.rem_euclid(m))
 }
 
-impl Modulo for i128 {
-    fn inv(self) -> Self {
-        let (mut r1, mut u1, mut r2, mut u2) = (self, 1, BIG_SIZE, 0);
+enum Instruction {
+    DealNewStack,
+    Cut(i128),
+    DealIncrement(i128),
+}
 
-        while r2 != 0 {
-            let q = r1 / r2;
-            (r1, u1, r2, u2) = (r2, u2, r1 - q * r2, u1 - q * u2);
-        }
+fn parse_instructions(input: &str) -> Result<Vec<Instruction>> {
+    input
+        .lines()
+        .map(|line| {
+            if line == "deal into new stack" {
+                Ok(Instruction::DealNewStack)
+            } else if line.starts_with("cut") {
+                Ok(Instruction::Cut(
+                    line.split_ascii_whitespace().last().value()?.parse()?,
+                ))
+            } else if line.starts_with("deal with increment") {
+                Ok(Instruction::DealIncrement(
+                    line.split_ascii_whitespace().last().value()?.parse()?,
+                ))
+            } else {
+                bail!("unkwnown instruction: {line}")
+            }
+        })
+        .try_collect()
+}
 
-        u1.rem_euclid(BIG_SIZE)
+/// Shuffle of a deck, as the affine map `position -> scale * position + offset` modulo the deck size
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct Shuffle {
+    size: i128,
+    scale: i128,
+    offset: i128,
+}
+
+impl Shuffle {
+    fn identity(size: i128) -> Self {
+        Self {
+            size,
+            scale: 1,
+            offset: 0,
+        }
     }
 
-    fn add(self, other: Self) -> Self {
-        (self + other).rem_euclid(BIG_SIZE)
+    fn from_instruction(instruction: &Instruction, size: i128) -> Result<Self> {
+        let (scale, offset) = match *instruction {
+            Instruction::DealNewStack => (-1, -1),
+            Instruction::Cut(n) => (1, -n),
+            Instruction::DealIncrement(n) => {
+                ensure!(
+                    mod_inverse(n, size).is_some(),
+                    "increment {n} is not coprime with deck size {size}"
+                );
+                (n, 0)
+            }
+        };
+
+        Ok(Self {
+            size,
+            scale: scale.rem_euclid(size),
+            offset: offset.rem_euclid(size),
+        })
     }
 
-    fn sub(self, other: Self) -> Self {
-        (self - other).rem_euclid(BIG_SIZE)
+    fn from_instructions(instructions: &[Instruction], size: i128) -> Result<Self> {
+        instructions
+            .iter()
+            .try_fold(Self::identity(size), |shuffle, instruction| {
+                Ok(shuffle.then(&Self::from_instruction(instruction, size)?))
+            })
     }
 
-    fn mul(self, other: Self) -> Self {
-        (self * other).rem_euclid(BIG_SIZE)
+    /// Shuffle applying `self` followed by `other`
+    fn then(&self, other: &Self) -> Self {
+        Self {
+            size: self.size,
+            scale: (other.scale * self.scale).rem_euclid(self.size),
+            offset: (other.scale * self.offset + other.offset).rem_euclid(self.size),
+        }
     }
 
-    fn power(mut self, mut other: Self) -> Self {
-        self = self.rem_euclid(BIG_SIZE);
+    /// Shuffle restoring the original order of the deck
+    fn inverse(&self) -> Result<Self> {
+        let inv_scale = mod_inverse(self.scale, self.size).value()?;
 
-        let mut res = 1;
-        while other > 0 {
-            if other & 1 != 0 {
-                res = res.mul(self);
+        Ok(Self {
+            size: self.size,
+            scale: inv_scale,
+            offset: (-inv_scale * self.offset).rem_euclid(self.size),
+        })
+    }
+
+    /// Shuffle repeated `n` times, using exponentiation by squaring
+    fn power(&self, n: i128) -> Result<Self> {
+        let (mut base, mut n) = match n < 0 {
+            true => (self.inverse()?, -n),
+            false => (*self, n),
+        };
+
+        let mut result = Self::identity(self.size);
+        while n > 0 {
+            if n & 1 != 0 {
+                result = result.then(&base);
             }
-            self = self.mul(self);
-            other >>= 1;
+            base = base.then(&base);
+            n >>= 1;
         }
-        res
+
+        Ok(result)
     }
-}
 
-enum Instruction {
-    DealNewStack,
-    Cut(i128),
-    DealIncrement(i128),
+    /// Position of a card after the shuffle
+    fn position_of(&self, card: i128) -> i128 {
+        (self.scale * card + self.offset).rem_euclid(self.size)
+    }
+
+    /// Card at a position after the shuffle
+    fn card_at(&self, position: i128) -> Result<i128> {
+        Ok(self.inverse()?.position_of(position))
+    }
 }
 
-fn compute_card_position(
-    instructions: &[Instruction],
-    size: i128,
-    mut current_position: i128,
-) -> i128 {
+/// Shuffle a deck card by card, returning the card at each position
+fn simulate(instructions: &[Instruction], size: usize) -> Vec<i128> {
+    let mut deck = (0..size as i128).collect_vec();
+    let mut buffer = vec![0; size];
+
     for instruction in instructions {
         match *instruction {
-            Instruction::DealNewStack => current_position = size - 1 - current_position,
-            Instruction::Cut(n) => current_position = (current_position - n) % size,
-            Instruction::DealIncrement(n) => current_position = (current_position * n) % size,
+            Instruction::DealNewStack => deck.reverse(),
+            Instruction::Cut(n) => deck.rotate_left(n.rem_euclid(size as i128) as usize),
+            Instruction::DealIncrement(n) => {
+                let increment = n.rem_euclid(size as i128) as usize;
+                for (index, &card) in deck.iter().enumerate() {
+                    buffer[(index * increment) % size] = card;
+                }
+                std::mem::swap(&mut deck, &mut buffer);
+            }
         }
     }
-    current_position
+
+    deck
+}
+
+/// Check the shuffle algebra against a simulation of the deck
+fn check(instructions: &[Instruction], size: usize) -> Result<()> {
+    let deck = simulate(instructions, size);
+    let shuffle = Shuffle::from_instructions(instructions, size as i128)?;
+
+    for (position, &card) in deck.iter().enumerate() {
+        ensure!(
+            shuffle.position_of(card) == position as i128,
+            "wrong position for card {card}"
+        );
+        ensure!(
+            shuffle.card_at(position as i128)? == card,
+            "wrong card at position {position}"
+        );
+    }
+
+    let repeated = (0..3).fold(Shuffle::identity(size as i128), |acc, _| acc.then(&shuffle));
+    ensure!(shuffle.power(3)? == repeated, "wrong power of shuffle");
+    ensure!(
+        shuffle.power(-1)?.then(&shuffle) == Shuffle::identity(size as i128),
+        "wrong inverse of shuffle"
+    );
+
+    Ok(())
 }
 
 fn main() -> Result<()> {
     let input = setup(file!())?;
     let input = String::from_utf8_lossy(&input);
 
-    let instructions: Vec<_> = input
-        .lines()
-        .map(|line| {
-            if line == "deal into new stack" {
-                Ok(Instruction::DealNewStack)
-            } else if line.starts_with("cut") {
-                Ok(Instruction::Cut(
-                    line.split_ascii_whitespace().last().value()?.parse()?,
-                ))
-            } else if line.starts_with("deal with increment") {
-                Ok(Instruction::DealIncrement(
-                    line.split_ascii_whitespace().last().value()?.parse()?,
-                ))
-            } else {
-                bail!("unkwnown instruction: {line}")
-            }
-        })
-        .try_collect()?;
-
-    let result1 = compute_card_position(&instructions, 10007, 2019);
+    let instructions = parse_instructions(&input)?;
 
-    let reversed_instructions = instructions
-        .iter()
-        .rev()
-        .map(|instruction| match *instruction {
-            Instruction::DealNewStack => Instruction::DealNewStack,
-            Instruction::Cut(n) => Instruction::Cut(-n),
-            Instruction::DealIncrement(n) => Instruction::DealIncrement(n.inv()),
-        })
-        .collect_vec();
-
-    let position_0 = 2020_i128;
-    let position_1 = compute_card_position(&reversed_instructions, BIG_SIZE, position_0);
-    let position_2 = compute_card_position(&reversed_instructions, BIG_SIZE, position_1);
+    if flag("--check") {
+        for (example, expected_deck) in EXAMPLES {
+            let example_instructions = parse_instructions(example)?;
+            ensure!(
+                simulate(&example_instructions, 10) == expected_deck,
+                "wrong simulation"
+            );
+            check(&example_instructions, 10)?;
+        }
+        check(&instructions, SMALL_SIZE as usize)?;
+        println!("all checks passed\n");
+    }
 
-    let inv_0_1 = (position_0.sub(position_1)).inv();
-    let scale = (position_1 - position_2).mul(inv_0_1);
-    let shift = position_1.sub(scale.mul(position_0));
-    let scale_pow_n = scale.power(101741582076661);
+    let result1 = Shuffle::from_instructions(&instructions, SMALL_SIZE)?.position_of(2019);
 
-    let result2 = scale_pow_n
-        .mul(position_0)
-        .add((scale_pow_n - 1).mul((scale - 1).inv()).mul(shift));
+    let result2 = Shuffle::from_instructions(&instructions, BIG_SIZE)?
+        .power(REPETITIONS)?
+        .card_at(2020)?;
 
     println!("{result1}");
     println!("{result2}");
@@ -1,3 +1,4 @@
+use aoc::dot::Dot;
 use aoc::*;
 
 use itertools::Itertools;
@@ -33,6 +34,20 @@ fn main() -> Result<()> {
         inverted_graph.entry(bag).or_default();
     }
 
+    if flag("--dot") {
+        let mut dot = Dot::digraph();
+        dot.node(STARTING_BAG, &[("style", "filled"), ("fillcolor", "gold")]);
+
+        for (bag, content) in graph.iter().sorted_unstable_by_key(|&(&bag, _)| bag) {
+            for (bag_count, content_bag) in content {
+                dot.edge(bag, content_bag, &[("label", &bag_count.to_string())]);
+            }
+        }
+
+        print!("{dot}");
+        return Ok(());
+    }
+
     let mut visited = HashSet::from([STARTING_BAG]);
     let mut queue = inverted_graph[STARTING_BAG].clone();
 
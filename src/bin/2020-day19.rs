@@ -1,86 +1,41 @@
+use aoc::dag::{Dag, Evaluator};
 use aoc::*;
 
 use eyre::ensure;
 use itertools::Itertools;
 use smallvec::SmallVec;
 
-use std::collections::{HashMap, VecDeque};
+use std::collections::HashMap;
 use std::iter;
 
-fn main() -> Result<()> {
-    let input = setup(file!())?;
-    let input = String::from_utf8_lossy(&input);
-
-    let (rules_input, messages_input) = input.split("\n\n").next_tuple().value()?;
-
-    let messages = messages_input
-        .lines()
-        .map(|line| line.as_bytes())
-        .collect_vec();
-
-    let mut graph = HashMap::new();
-    let mut inverted_graph = HashMap::<_, Vec<_>>::new();
-    let mut valid_rule_messages = HashMap::<_, Vec<_>>::new();
-
-    for line in rules_input.lines() {
-        let (name, content) = line.split(": ").next_tuple().value()?;
-
-        inverted_graph.entry(name).or_default();
-
-        match *content.as_bytes() {
-            [b'"', letter, b'"'] => {
-                valid_rule_messages.insert(name, vec![SmallVec::from_slice(&[letter])]);
-                graph.insert(name, SmallVec::new());
-            }
-            _ => {
-                let sub_rules: SmallVec<[SmallVec<[_; 2]>; 2]> = content
-                    .split('|')
-                    .map(|x| x.split_ascii_whitespace().collect())
-                    .collect();
-
-                for &dependency in sub_rules.iter().flatten() {
-                    inverted_graph.entry(dependency).or_default().push(name);
-                }
-
-                graph.insert(name, sub_rules);
-            }
-        }
-    }
-
-    for dependencies in inverted_graph.values_mut() {
-        dependencies.sort_unstable();
-        dependencies.dedup();
-    }
+enum Rule<'a> {
+    Letter(u8),
+    SubRules(SmallVec<[SmallVec<[&'a str; 2]>; 2]>),
+}
 
-    let mut queue: VecDeque<_> = valid_rule_messages
-        .keys()
-        .flat_map(|&name| &inverted_graph[name])
-        .copied()
-        .filter(|&name| {
-            (graph[name].iter().flatten()).all(|&dep| valid_rule_messages.contains_key(dep))
-        })
-        .sorted_unstable()
-        .dedup()
-        .collect();
-
-    while let Some(name) = queue.pop_front() {
-        if name == "0" {
-            break;
-        }
+impl Rule<'_> {
+    fn valid_messages(
+        &self,
+        values: &HashMap<&str, Vec<SmallVec<[u8; 16]>>>,
+    ) -> Vec<SmallVec<[u8; 16]>> {
+        let sub_rules = match self {
+            Rule::Letter(letter) => return vec![SmallVec::from_slice(&[*letter])],
+            Rule::SubRules(sub_rules) => sub_rules,
+        };
 
         let mut valid_messages = Vec::new();
 
-        for possibilities in &graph[name] {
+        for possibilities in sub_rules {
             let combinations: SmallVec<[_; 2]> = possibilities
                 .iter()
-                .map(|&x| valid_rule_messages[x].as_slice())
+                .map(|&x| values[x].as_slice())
                 .collect();
 
             let mut current_combination: SmallVec<[_; 2]> =
                 SmallVec::from_elem(0, possibilities.len());
 
             'outer: loop {
-                let mut valid_message = SmallVec::<[_; 16]>::new();
+                let mut valid_message = SmallVec::new();
                 for (&values, &index) in combinations.iter().zip(&current_combination) {
                     valid_message.extend_from_slice(&values[index]);
                 }
@@ -101,27 +56,56 @@ fn main() -> Result<()> {
             }
         }
 
-        valid_rule_messages.insert(name, valid_messages);
+        valid_messages
+    }
+}
 
-        queue.extend(inverted_graph[name].iter().copied().filter(|&x| {
-            (graph[x].iter().flatten()).all(|&dep| valid_rule_messages.contains_key(dep))
-        }));
+fn main() -> Result<()> {
+    let input = setup(file!())?;
+    let input = String::from_utf8_lossy(&input);
+
+    let (rules_input, messages_input) = input.split("\n\n").next_tuple().value()?;
+
+    let messages = messages_input
+        .lines()
+        .map(|line| line.as_bytes())
+        .collect_vec();
+
+    let mut rules = Dag::new();
+
+    for line in rules_input.lines() {
+        let (name, content) = line.split(": ").next_tuple().value()?;
+
+        match *content.as_bytes() {
+            [b'"', letter, b'"'] => rules.insert(name, Rule::Letter(letter), []),
+            _ => {
+                let sub_rules: SmallVec<[SmallVec<[_; 2]>; 2]> = content
+                    .split('|')
+                    .map(|x| x.split_ascii_whitespace().collect())
+                    .collect();
+
+                let dependencies = sub_rules.iter().flatten().copied().collect_vec();
+                rules.insert(name, Rule::SubRules(sub_rules), dependencies);
+            }
+        }
     }
 
-    ensure!(
-        graph["0"].as_slice() == [SmallVec::from_buf(["8", "11"])],
-        "invalid input"
-    );
+    let check_sub_rules = |name, expected: &[&str]| match rules.node(name) {
+        Some(Rule::SubRules(sub_rules)) => {
+            sub_rules.len() == 1 && sub_rules[0].as_slice() == expected
+        }
+        _ => false,
+    };
 
-    ensure!(
-        graph["8"].as_slice() == [SmallVec::from_buf(["42"])],
-        "invalid input"
-    );
+    ensure!(check_sub_rules("0", &["8", "11"]), "invalid input");
+    ensure!(check_sub_rules("8", &["42"]), "invalid input");
+    ensure!(check_sub_rules("11", &["42", "31"]), "invalid input");
 
-    ensure!(
-        graph["11"].as_slice() == [SmallVec::from_buf(["42", "31"])],
-        "invalid input"
-    );
+    let mut evaluator = Evaluator::new(&rules, Rule::valid_messages);
+    evaluator.get("31")?;
+    evaluator.get("42")?;
+
+    let valid_rule_messages = evaluator.values();
 
     let rule_31_messages = valid_rule_messages.get("31").value()?;
     let rule_42_messages = valid_rule_messages.get("42").value()?;
@@ -1,17 +1,15 @@
+use aoc::rotation::{Mat3x3, Matrix, Vec3, Vector, rotation_group};
 use aoc::*;
 
-use eyre::ensure;
-use itertools::{Itertools, iproduct};
+use eyre::{bail, ensure};
+use itertools::Itertools;
 use regex::Regex;
-use smallvec::SmallVec;
 
-use std::collections::VecDeque;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::iter;
 
-type Vec3 = [i64; 3];
-type Mat3x3 = [Vec3; 3];
-
-const N_PAIRS: usize = n_pairs(12);
+const DEFAULT_THRESHOLD: usize = 12;
+const DETECTION_RANGE: i64 = 1000;
 
 const fn n_pairs(n_common_beacons: usize) -> usize {
     n_common_beacons * (n_common_beacons - 1) / 2
@@ -26,9 +24,13 @@ impl Frame {
     fn identity() -> Self {
         Self {
             position: [0; 3],
-            orientation: [[1, 0, 0], [0, 1, 0], [0, 0, 1]],
+            orientation: Mat3x3::identity(),
         }
     }
+
+    fn transform(&self, beacon: &Vec3) -> Vec3 {
+        self.position.add(&beacon.apply(&self.orientation))
+    }
 }
 
 struct Scanner {
@@ -36,75 +38,18 @@ struct Scanner {
     beacons: Vec<Vec3>,
 }
 
-trait Position {
-    fn add(&self, rhs: &Self) -> Self;
-    fn sub(&self, rhs: &Self) -> Self;
-    fn abs(&self) -> Self;
-    fn scale(&self, factor: i64) -> Self;
-    fn norm(&self) -> i64;
-    fn norm2(&self) -> i64;
-    fn dot(&self, rhs: &Self) -> i64;
-    fn apply(&self, matrix: &Mat3x3) -> Self;
-}
-
-impl Position for Vec3 {
-    fn add(&self, rhs: &Self) -> Self {
-        [self[0] + rhs[0], self[1] + rhs[1], self[2] + rhs[2]]
-    }
-
-    fn sub(&self, rhs: &Self) -> Self {
-        [self[0] - rhs[0], self[1] - rhs[1], self[2] - rhs[2]]
-    }
-
-    fn abs(&self) -> Self {
-        [self[0].abs(), self[1].abs(), self[2].abs()]
-    }
-
-    fn scale(&self, factor: i64) -> Self {
-        [factor * self[0], factor * self[1], factor * self[2]]
-    }
-
-    fn norm(&self) -> i64 {
-        self[0].abs() + self[1].abs() + self[2].abs()
-    }
-
-    fn norm2(&self) -> i64 {
-        self[0] * self[0] + self[1] * self[1] + self[2] * self[2]
-    }
-
-    fn dot(&self, rhs: &Self) -> i64 {
-        self[0] * rhs[0] + self[1] * rhs[1] + self[2] * rhs[2]
-    }
-
-    fn apply(&self, matrix: &Mat3x3) -> Self {
-        [
-            self.dot(&matrix[0]),
-            self.dot(&matrix[1]),
-            self.dot(&matrix[2]),
-        ]
+impl Scanner {
+    fn global_beacons(&self) -> Option<HashSet<Vec3>> {
+        let frame = self.frame.as_ref()?;
+        Some(
+            self.beacons
+                .iter()
+                .map(|beacon| frame.transform(beacon))
+                .collect(),
+        )
     }
 }
 
-fn matmul(m1: &Mat3x3, m2: &Mat3x3) -> Mat3x3 {
-    [
-        [
-            m1[0][0] * m2[0][0] + m1[0][1] * m2[1][0] + m1[0][2] * m2[2][0],
-            m1[0][0] * m2[0][1] + m1[0][1] * m2[1][1] + m1[0][2] * m2[2][1],
-            m1[0][0] * m2[0][2] + m1[0][1] * m2[1][2] + m1[0][2] * m2[2][2],
-        ],
-        [
-            m1[1][0] * m2[0][0] + m1[1][1] * m2[1][0] + m1[1][2] * m2[2][0],
-            m1[1][0] * m2[0][1] + m1[1][1] * m2[1][1] + m1[1][2] * m2[2][1],
-            m1[1][0] * m2[0][2] + m1[1][1] * m2[1][2] + m1[1][2] * m2[2][2],
-        ],
-        [
-            m1[2][0] * m2[0][0] + m1[2][1] * m2[1][0] + m1[2][2] * m2[2][0],
-            m1[2][0] * m2[0][1] + m1[2][1] * m2[1][1] + m1[2][2] * m2[2][1],
-            m1[2][0] * m2[0][2] + m1[2][1] * m2[1][2] + m1[2][2] * m2[2][2],
-        ],
-    ]
-}
-
 type PairDist = ([usize; 2], i64);
 type PairDistList = Vec<Vec<PairDist>>;
 
@@ -116,8 +61,8 @@ fn compute_pair_dists_list(scanners: &[Scanner]) -> Result<PairDistList> {
                 .beacons
                 .iter()
                 .enumerate()
-                .tuple_combinations()
-                .map(|((index1, b1), (index2, b2))| ([index1, index2], b2.sub(b1).norm2()))
+                .array_combinations()
+                .map(|[(index1, b1), (index2, b2)]| ([index1, index2], b2.sub(b1).norm2()))
                 .sorted_unstable_by_key(|&(_, dist)| dist)
                 .collect_vec();
 
@@ -131,118 +76,183 @@ fn compute_pair_dists_list(scanners: &[Scanner]) -> Result<PairDistList> {
         .try_collect()
 }
 
-type Overlap = [(usize, [Vec3; 2], [Vec3; 2]); 2];
+/// Pair of scanners sharing enough beacon pairs with the same distance to possibly overlap
+struct Overlap {
+    scanners: [usize; 2],
+    matched_pairs: Vec<[[usize; 2]; 2]>,
+}
 
-fn compute_overlaps(scanners: &[Scanner], pair_dists_list: &PairDistList) -> VecDeque<Overlap> {
+fn compute_overlaps(pair_dists_list: &PairDistList, threshold: usize) -> Vec<Overlap> {
     pair_dists_list
         .iter()
         .enumerate()
-        .tuple_combinations()
-        .filter_map(|((index1, p1), (index2, p2))| {
+        .array_combinations()
+        .filter_map(|[(index1, p1), (index2, p2)]| {
             let mut p2_iter = p2.iter();
 
-            let mut unique_pairs_iter = p1.iter().filter_map(|&(pair_indices_1, dist1)| {
-                p2_iter
-                    .take_while_ref(|&&(_, dist2)| dist2 <= dist1)
-                    .find(|&&(_, dist2)| dist2 == dist1)
-                    .map(|&(pair_indices_2, _)| [pair_indices_1, pair_indices_2])
-            });
+            let matched_pairs = p1
+                .iter()
+                .filter_map(|&(pair_indices_1, dist1)| {
+                    p2_iter
+                        .take_while_ref(|&&(_, dist2)| dist2 <= dist1)
+                        .find(|&&(_, dist2)| dist2 == dist1)
+                        .map(|&(pair_indices_2, _)| [pair_indices_1, pair_indices_2])
+                })
+                .collect_vec();
 
-            let two_pairs: SmallVec<[[[usize; 2]; 2]; 2]> =
-                unique_pairs_iter.by_ref().take(2).collect();
+            (matched_pairs.len() >= n_pairs(threshold)).then_some(Overlap {
+                scanners: [index1, index2],
+                matched_pairs,
+            })
+        })
+        .collect()
+}
 
-            let count = two_pairs.len() + unique_pairs_iter.count();
+/// Find the frame of a scanner from matched beacon pairs with an already placed scanner,
+/// verifying that at least `threshold` beacons coincide.
+fn align(
+    rotations: &[Mat3x3],
+    placed: &Scanner,
+    placed_beacons: &HashSet<Vec3>,
+    scanner: &Scanner,
+    matched_pairs: impl Iterator<Item = ([usize; 2], [usize; 2])>,
+    threshold: usize,
+) -> Option<Frame> {
+    let placed_frame = placed.frame.as_ref()?;
+
+    for (placed_pair, pair) in matched_pairs {
+        let a0 = placed_frame.transform(&placed.beacons[placed_pair[0]]);
+        let a1 = placed_frame.transform(&placed.beacons[placed_pair[1]]);
+        let placed_diff = a1.sub(&a0);
+
+        for (rotation, [i0, i1]) in rotations
+            .iter()
+            .cartesian_product([pair, [pair[1], pair[0]]])
+        {
+            let [b0, b1] = [scanner.beacons[i0], scanner.beacons[i1]];
+            if b1.sub(&b0).apply(rotation) != placed_diff {
+                continue;
+            }
 
-            let (N_PAIRS.., &[[idx_pair_1a, idx_pair_2a], [idx_pair_1b, idx_pair_2b]]) =
-                (count, two_pairs.as_slice())
-            else {
-                return None;
+            let frame = Frame {
+                position: a0.sub(&b0.apply(rotation)),
+                orientation: *rotation,
             };
 
-            let beacons1 = &scanners[index1].beacons;
-            let pair_1a = [beacons1[idx_pair_1a[0]], beacons1[idx_pair_1a[1]]];
-            let pair_1b = [beacons1[idx_pair_1b[0]], beacons1[idx_pair_1b[1]]];
+            let common_count = (scanner.beacons.iter())
+                .filter(|beacon| placed_beacons.contains(&frame.transform(beacon)))
+                .count();
 
-            let beacons2 = &scanners[index2].beacons;
-            let pair_2a = [beacons2[idx_pair_2a[0]], beacons2[idx_pair_2a[1]]];
-            let pair_2b = [beacons2[idx_pair_2b[0]], beacons2[idx_pair_2b[1]]];
+            if common_count >= threshold {
+                return Some(frame);
+            }
+        }
+    }
 
-            Some([(index1, pair_1a, pair_1b), (index2, pair_2a, pair_2b)])
-        })
-        .collect()
+    None
 }
 
-fn compute_scanner_frames(scanners: &mut [Scanner], mut overlaps: VecDeque<Overlap>) -> Result<()> {
-    while let Some(mut overlap) = overlaps.pop_front() {
-        match (&scanners[overlap[0].0].frame, &scanners[overlap[1].0].frame) {
-            (Some(_), None) => (),
-            (Some(_), Some(_)) => continue,
-            (None, Some(_)) => overlap.swap(0, 1),
-            (None, None) => {
-                overlaps.push_back(overlap);
-                continue;
-            }
-        }
+/// Place all scanners reachable from the first one, returning the indices of the unplaced scanners
+fn compute_scanner_frames(
+    scanners: &mut [Scanner],
+    overlaps: &[Overlap],
+    threshold: usize,
+) -> Vec<usize> {
+    let rotations = rotation_group();
+
+    let mut neighbors = HashMap::<_, Vec<_>>::new();
+    for (index, overlap) in overlaps.iter().enumerate() {
+        let [index1, index2] = overlap.scanners;
+        neighbors
+            .entry(index1)
+            .or_default()
+            .push((index2, index, false));
+        neighbors
+            .entry(index2)
+            .or_default()
+            .push((index1, index, true));
+    }
 
-        let [(index1, pair_1a, pair_1b), (index2, pair_2a, pair_2b)] = overlap;
+    let mut global_beacons = scanners.iter().map(Scanner::global_beacons).collect_vec();
 
-        let diff1 = pair_1a[1].sub(&pair_1a[0]);
-        let diff2 = pair_2a[1].sub(&pair_2a[0]);
+    let mut queue = (0..scanners.len())
+        .filter(|&index| scanners[index].frame.is_some())
+        .collect::<VecDeque<_>>();
 
-        ensure!(
-            iter::chain(diff1, diff2).all(|v| v != 0),
-            "all differences of beacon pairs should have non-zero coordinates"
-        );
+    while let Some(placed_index) = queue.pop_front() {
+        for &(index, overlap_index, swapped) in neighbors.get(&placed_index).into_iter().flatten() {
+            if scanners[index].frame.is_some() {
+                continue;
+            }
 
-        let diff1_abs = diff1.abs();
-        let diff2_abs = diff2.abs();
+            let matched_pairs =
+                overlaps[overlap_index]
+                    .matched_pairs
+                    .iter()
+                    .map(|&[pair1, pair2]| {
+                        if swapped {
+                            (pair2, pair1)
+                        } else {
+                            (pair1, pair2)
+                        }
+                    });
+
+            let Some(placed_beacons) = &global_beacons[placed_index] else {
+                continue;
+            };
 
-        let permutations = [
-            [0, 1, 2],
-            [0, 2, 1],
-            [1, 0, 2],
-            [1, 2, 0],
-            [2, 0, 1],
-            [2, 1, 0],
-        ];
+            let frame = align(
+                &rotations,
+                &scanners[placed_index],
+                placed_beacons,
+                &scanners[index],
+                matched_pairs,
+                threshold,
+            );
 
-        let &[ix, iy, iz] = permutations
-            .iter()
-            .find(|&&[ix, iy, iz]| [diff1_abs[ix], diff1_abs[iy], diff1_abs[iz]] == diff2_abs)
-            .value()?;
+            if let Some(frame) = frame {
+                scanners[index].frame = Some(frame);
+                global_beacons[index] = scanners[index].global_beacons();
+                queue.push_back(index);
+            }
+        }
+    }
 
-        let mut axis = [[0; 3]; 3];
-        axis[ix] = [1, 0, 0].scale(diff2[0].signum() * diff1[ix].signum());
-        axis[iy] = [0, 1, 0].scale(diff2[1].signum() * diff1[iy].signum());
-        axis[iz] = [0, 0, 1].scale(diff2[2].signum() * diff1[iz].signum());
+    (0..scanners.len())
+        .filter(|&index| scanners[index].frame.is_none())
+        .collect()
+}
 
-        let pair_2a_corr = [pair_2a[0].apply(&axis), pair_2a[1].apply(&axis)];
-        let pair_2b_corr = [pair_2b[0].apply(&axis), pair_2b[1].apply(&axis)];
+/// Check that each scanner detects all the beacons of other scanners which are in its detection range
+fn check_consistency(scanners: &[Scanner]) -> Result<()> {
+    let global_beacons: Vec<_> = scanners
+        .iter()
+        .map(|s| s.global_beacons().value())
+        .try_collect()?;
+    let frames: Vec<_> = scanners
+        .iter()
+        .map(|s| s.frame.as_ref().value())
+        .try_collect()?;
 
-        let (sign, offset) = iproduct!(
-            [1, -1],
-            iproduct!(pair_1a, pair_2a_corr).chain(iproduct!(pair_1b, pair_2b_corr))
-        )
-        .map(|(sign, (p1, p2c))| (sign, p1.sub(&p2c.scale(sign))))
-        .sorted_unstable_by_key(|&(_, x)| x)
-        .dedup_by_with_count(|(_, x1), (_, x2)| x1 == x2)
-        .find(|&(count, _)| count == 4)
-        .map(|(_, x)| x)
-        .value()?;
+    for [index1, index2] in (0..scanners.len()).array_combinations() {
+        for (i, j) in [(index1, index2), (index2, index1)] {
+            let position = frames[j].position;
+
+            let missing = global_beacons[i].iter().find(|beacon| {
+                beacon
+                    .sub(&position)
+                    .abs()
+                    .iter()
+                    .all(|&x| x <= DETECTION_RANGE)
+                    && !global_beacons[j].contains(*beacon)
+            });
 
-        let frame1 = scanners[index1].frame.as_ref().value()?;
-
-        scanners[index2].frame = Some(Frame {
-            position: frame1.position.add(&offset.apply(&frame1.orientation)),
-            orientation: matmul(
-                &frame1.orientation,
-                &[
-                    axis[0].scale(sign),
-                    axis[1].scale(sign),
-                    axis[2].scale(sign),
-                ],
-            ),
-        });
+            if let Some(beacon) = missing {
+                bail!(
+                    "inconsistent alignment: scanner {j} should detect beacon {beacon:?} of scanner {i}"
+                );
+            }
+        }
     }
 
     Ok(())
@@ -252,6 +262,9 @@ fn main() -> Result<()> {
     let input = setup(file!())?;
     let input = String::from_utf8_lossy(&input);
 
+    let threshold = option("--threshold")?.unwrap_or(DEFAULT_THRESHOLD);
+    ensure!(threshold >= 2, "overlap threshold should be at least 2");
+
     let re = Regex::new(r#"(?m)^(.+?),(.+?),(.+?)$"#)?;
 
     let mut scanners: Vec<_> = input
@@ -270,25 +283,39 @@ fn main() -> Result<()> {
         })
         .try_collect()?;
 
-    scanners[0].frame = Some(Frame::identity());
+    scanners.first_mut().value()?.frame = Some(Frame::identity());
 
     let pair_dists_list = compute_pair_dists_list(&scanners)?;
-    let overlaps = compute_overlaps(&scanners, &pair_dists_list);
-    compute_scanner_frames(&mut scanners, overlaps)?;
+    let overlaps = compute_overlaps(&pair_dists_list, threshold);
+    let unplaced = compute_scanner_frames(&mut scanners, &overlaps, threshold);
+
+    ensure!(
+        unplaced.is_empty(),
+        "unable to place scanners: {}",
+        unplaced.iter().join(", ")
+    );
+
+    check_consistency(&scanners)?;
 
     let frames: Vec<_> = scanners
         .iter()
         .map(|s| s.frame.as_ref().value())
         .try_collect()?;
 
+    if flag("--poses") {
+        for (index, frame) in frames.iter().enumerate() {
+            let [x, y, z] = frame.position;
+            println!(
+                "scanner {index}: position {x},{y},{z}, orientation {:?}",
+                frame.orientation
+            );
+        }
+        println!();
+    }
+
     let mut unique_beacons = iter::zip(&scanners, &frames)
-        .map(|(scanner, frame)| {
-            Ok(scanner
-                .beacons
-                .iter()
-                .map(|beacon| frame.position.add(&(beacon.apply(&frame.orientation)))))
-        })
-        .try_process(|iter| iter.flatten().collect_vec())?;
+        .flat_map(|(scanner, frame)| scanner.beacons.iter().map(|beacon| frame.transform(beacon)))
+        .collect_vec();
 
     unique_beacons.sort_unstable();
     unique_beacons.dedup();
@@ -297,8 +324,8 @@ fn main() -> Result<()> {
 
     let result2 = frames
         .iter()
-        .tuple_combinations()
-        .map(|(f1, f2)| f1.position.sub(&f2.position).norm())
+        .array_combinations()
+        .map(|[f1, f2]| f1.position.sub(&f2.position).norm())
         .max()
         .value()?;
 
@@ -1,45 +1,100 @@
+use aoc::interval::IntervalSet;
 use aoc::*;
 
+use eyre::bail;
 use itertools::Itertools;
 use regex::Regex;
 
+const DEFAULT_ROW: i64 = 2_000_000;
+const DEFAULT_BOUND: i64 = 4_000_000;
+const TUNING_MULTIPLIER: i64 = 4_000_000;
+
 type Point = (i64, i64);
 
-fn find_x_range(
-    buffer: &mut Vec<Point>,
-    sensors_with_distance: &[(Point, i64)],
-    y: i64,
-) -> [Option<Point>; 2] {
-    buffer.clear();
-
-    buffer.extend(
-        (sensors_with_distance.iter()).flat_map(|((sx, sy), distance)| {
-            let diff_x = distance - (y - sy).abs();
-            (diff_x >= 0).then_some((sx - diff_x, sx + diff_x))
-        }),
-    );
-
-    buffer.sort_unstable();
-
-    let mut iter = buffer.iter().copied().coalesce(|r1, r2| {
-        if r2.0 <= r1.1 + 1 {
-            Ok((r1.0, r1.1.max(r2.1)))
-        } else {
-            Err((r1, r2))
-        }
+struct Sensor {
+    position: Point,
+    distance: i64,
+}
+
+impl Sensor {
+    fn covers(&self, (x, y): Point) -> bool {
+        (x - self.position.0).abs() + (y - self.position.1).abs() <= self.distance
+    }
+
+    /// Position in rotated coordinates `(x + y, x - y)`, where the sensor range is a square
+    fn rotated_position(&self) -> Point {
+        let (x, y) = self.position;
+        (x + y, x - y)
+    }
+}
+
+/// Compute the set of x coordinates covered by the sensors on a row
+fn find_x_ranges(sensors: &[Sensor], y: i64) -> IntervalSet {
+    (sensors.iter())
+        .flat_map(|sensor| {
+            let (sx, sy) = sensor.position;
+            let diff_x = sensor.distance - (y - sy).abs();
+            (diff_x >= 0).then_some(sx - diff_x..sx + diff_x + 1)
+        })
+        .collect()
+}
+
+/// Find the only position in `[0, bound]²` not covered by any sensor.
+///
+/// Such a position is just outside the range of its neighboring sensors, so it is located at the
+/// intersection of their boundary lines, which are the lines `u = const` and `v = const` in rotated
+/// coordinates `(u, v) = (x + y, x - y)`.
+fn find_uncovered_position(sensors: &[Sensor], bound: i64) -> Result<Point> {
+    let (u_lines, v_lines): (Vec<_>, Vec<_>) = (sensors.iter())
+        .flat_map(|sensor| {
+            let (u, v) = sensor.rotated_position();
+            let d = sensor.distance + 1;
+            [(u - d, v - d), (u + d, v + d)]
+        })
+        .unzip();
+
+    // Boundary lines can also meet the edges of the search area
+    let edge_points = [0, bound].into_iter().flat_map(|edge| {
+        (u_lines.iter().chain(&v_lines)).flat_map(move |&line| {
+            [
+                (edge, line - edge),
+                (line - edge, edge),
+                (edge, edge - line),
+                (line + edge, edge),
+            ]
+        })
     });
 
-    [iter.next(), iter.next()]
+    let line_points = (u_lines.iter().cartesian_product(&v_lines))
+        .filter(|&(u, v)| (u + v).rem_euclid(2) == 0)
+        .map(|(u, v)| ((u + v) / 2, (u - v) / 2));
+
+    let corners = [(0, 0), (0, bound), (bound, 0), (bound, bound)];
+
+    let candidates = (line_points.chain(edge_points).chain(corners))
+        .filter(|&(x, y)| (0..=bound).contains(&x) && (0..=bound).contains(&y))
+        .filter(|&position| sensors.iter().all(|sensor| !sensor.covers(position)))
+        .unique()
+        .collect_vec();
+
+    match candidates.as_slice() {
+        [] => bail!("no uncovered position found"),
+        &[position] => Ok(position),
+        _ => bail!("multiple uncovered positions found: {candidates:?}"),
+    }
 }
 
 fn main() -> Result<()> {
     let input = setup(file!())?;
     let input = String::from_utf8_lossy(&input);
 
+    let row = option("--row")?.unwrap_or(DEFAULT_ROW);
+    let bound = option("--bound")?.unwrap_or(DEFAULT_BOUND);
+
     let re =
         Regex::new(r#"(?m)^Sensor at x=(.+?), y=(.+?): closest beacon is at x=(.+?), y=(.+?)$"#)?;
 
-    let (sensors_with_distance, mut beacons): (Vec<_>, Vec<_>) = re
+    let (sensors, beacons): (Vec<_>, Vec<_>) = re
         .captures_iter(&input)
         .map(|cap| {
             let sx = cap[1].parse::<i64>()?;
@@ -47,36 +102,27 @@ fn main() -> Result<()> {
             let bx = cap[3].parse::<i64>()?;
             let by = cap[4].parse::<i64>()?;
             let distance = (bx - sx).abs() + (by - sy).abs();
-            Result::Ok((((sx, sy), distance), (bx, by)))
-        })
-        .try_process(|iter| iter.unzip())?;
-
-    beacons.sort_unstable();
-    beacons.dedup();
-
-    let mut buffer = Vec::new();
 
-    let middle_y = 2_000_000;
+            let sensor = Sensor {
+                position: (sx, sy),
+                distance,
+            };
 
-    let x_count = find_x_range(&mut buffer, &sensors_with_distance, middle_y)
-        .into_iter()
-        .flatten()
-        .map(|(min, max)| max - min + 1)
-        .sum::<i64>();
+            Result::Ok((sensor, (bx, by)))
+        })
+        .try_process(|iter| iter.unzip())?;
 
-    let y_beacons = beacons.iter().filter(|&&(_, by)| by == middle_y).count() as i64;
-    let result1 = x_count - y_beacons;
+    let row_beacons: IntervalSet = (beacons.iter())
+        .filter(|&&(_, by)| by == row)
+        .map(|&(bx, _)| bx..bx + 1)
+        .collect();
 
-    let max_y = 4_000_000;
+    let result1 = find_x_ranges(&sensors, row)
+        .difference(&row_beacons)
+        .total_length();
 
-    let result2 = (0..=max_y)
-        .find_map(
-            |y| match find_x_range(&mut buffer, &sensors_with_distance, y) {
-                [Some((_, left_max)), Some(_)] => Some((left_max + 1) * max_y + y),
-                _ => None,
-            },
-        )
-        .value()?;
+    let (x, y) = find_uncovered_position(&sensors, bound)?;
+    let result2 = x * TUNING_MULTIPLIER + y;
 
     println!("{result1}");
     println!("{result2}");
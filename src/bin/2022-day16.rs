@@ -1,71 +1,306 @@
+use aoc::dot::Dot;
 use aoc::*;
 
 use eyre::ensure;
 use itertools::Itertools;
 use regex::Regex;
+use smallvec::SmallVec;
 
 use std::collections::VecDeque;
 use std::collections::hash_map::{Entry, HashMap};
+use std::fmt::{self, Display};
 use std::iter;
 
+const START_VALVE: &str = "AA";
+
 struct Valve<'a> {
-    id: u8,
     name: &'a str,
     flow: u64,
     links: Vec<&'a str>,
 }
 
-struct State {
-    valve_id: u8,
-    remaining: u64,
+/// Set of valve indices, not limited to 64 valves
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+struct ValveSet {
+    words: SmallVec<[u64; 2]>,
+}
+
+impl ValveSet {
+    fn contains(&self, index: usize) -> bool {
+        (self.words.get(index / 64)).is_some_and(|word| word & (1 << (index % 64)) != 0)
+    }
+
+    fn with(&self, index: usize) -> Self {
+        let mut set = self.clone();
+        if set.words.len() <= index / 64 {
+            set.words.resize(index / 64 + 1, 0);
+        }
+        set.words[index / 64] |= 1 << (index % 64);
+        set
+    }
+
+    fn is_disjoint(&self, other: &Self) -> bool {
+        iter::zip(&self.words, &other.words).all(|(x, y)| x & y == 0)
+    }
+
+    fn union(&self, other: &Self) -> Self {
+        let (long, short) = match self.words.len() >= other.words.len() {
+            true => (self, other),
+            false => (other, self),
+        };
+
+        let mut set = long.clone();
+        for (x, y) in iter::zip(&mut set.words, &short.words) {
+            *x |= y;
+        }
+        set
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Opening {
+    valve: usize,
+    minute: u64,
+}
+
+/// Best way for a single agent to open a given set of valves
+struct Route {
+    open_valves: ValveSet,
+    pressure: u64,
+    openings: Vec<Opening>,
+}
+
+/// Best way for several agents to open a given set of valves, as indices in each agent's routes
+#[derive(Clone)]
+struct Combination {
+    open_valves: ValveSet,
     pressure: u64,
-    open_valves: u64,
+    routes: Vec<usize>,
 }
 
-fn compute_best_pressures(
-    connections: &[HashMap<u8, (Vec<u8>, u64)>],
-    start_valve_id: u8,
-    total_time: u64,
-) -> HashMap<u64, u64> {
-    let initial_state = State {
-        valve_id: start_valve_id,
-        remaining: total_time,
-        pressure: 0,
-        open_valves: 0,
-    };
-
-    let mut current_states = vec![initial_state];
-    let mut best_pressures = HashMap::new();
-
-    while let Some(state) = current_states.pop() {
-        best_pressures
-            .entry(state.open_valves)
-            .and_modify(|old| {
-                if state.pressure > *old {
-                    *old = state.pressure
+struct Plan<'a> {
+    pressure: u64,
+    schedules: Vec<Vec<(&'a str, u64)>>,
+}
+
+impl Display for Plan<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (agent, schedule) in self.schedules.iter().enumerate() {
+            let openings = (schedule.iter())
+                .map(|(name, minute)| format!("{name}@{minute}"))
+                .join(" ");
+            writeln!(f, "agent {}: {openings}", agent + 1)?;
+        }
+        write!(f, "total pressure: {}", self.pressure)
+    }
+}
+
+/// Valves with a non-zero flow rate (and the start valve), with the shortest distances between them
+struct Network<'a> {
+    names: Vec<&'a str>,
+    flows: Vec<u64>,
+    distances: Vec<Vec<Option<u64>>>,
+    start: usize,
+}
+
+impl<'a> Network<'a> {
+    fn new(valves: &[Valve<'a>], start_name: &str) -> Result<Self> {
+        let valves_map: HashMap<_, _> = (valves.iter())
+            .enumerate()
+            .map(|(index, valve)| (valve.name, index))
+            .collect();
+
+        for link in valves.iter().flat_map(|valve| &valve.links) {
+            ensure!(valves_map.contains_key(link), "unknown valve: {link}");
+        }
+
+        let start_index = *valves_map.get(start_name).value()?;
+
+        let kept = (0..valves.len())
+            .filter(|&index| index == start_index || valves[index].flow > 0)
+            .collect_vec();
+
+        let distances = (kept.iter())
+            .map(|&source| {
+                let mut distances = HashMap::from([(source, 0)]);
+                let mut current = VecDeque::from([source]);
+
+                while let Some(index) = current.pop_front() {
+                    let distance = distances[&index];
+                    for link in &valves[index].links {
+                        if let Entry::Vacant(entry) = distances.entry(valves_map[link]) {
+                            entry.insert(distance + 1);
+                            current.push_back(valves_map[link]);
+                        }
+                    }
                 }
+
+                (kept.iter())
+                    .map(|target| distances.get(target).copied())
+                    .collect()
             })
-            .or_insert(state.pressure);
+            .collect();
+
+        Ok(Self {
+            names: kept.iter().map(|&index| valves[index].name).collect(),
+            flows: kept.iter().map(|&index| valves[index].flow).collect(),
+            distances,
+            start: kept
+                .iter()
+                .position(|&index| index == start_index)
+                .value()?,
+        })
+    }
+
+    /// Compute the best route of a single agent for each set of opened valves
+    fn compute_routes(&self, total_time: u64) -> Vec<Route> {
+        let mut best_routes: HashMap<ValveSet, Route> = HashMap::new();
 
-        current_states.extend(connections[state.valve_id as usize].iter().flat_map(
-            |(&target, &(ref path, target_flow))| {
-                if target_flow == 0 || state.open_valves & (1 << target as u64) != 0 {
+        let initial_route = Route {
+            open_valves: ValveSet::default(),
+            pressure: 0,
+            openings: Vec::new(),
+        };
+
+        let mut current_states = vec![(self.start, total_time, initial_route)];
+
+        while let Some((valve, remaining, route)) = current_states.pop() {
+            current_states.extend((0..self.names.len()).filter_map(|target| {
+                if self.flows[target] == 0 || route.open_valves.contains(target) {
                     return None;
                 }
 
-                let reward = target_flow * state.remaining.checked_sub(path.len() as u64 + 1)?;
+                let remaining = remaining.checked_sub(self.distances[valve][target]? + 1)?;
+                let minute = total_time - remaining;
+
+                let mut openings = route.openings.clone();
+                openings.push(Opening {
+                    valve: target,
+                    minute,
+                });
+
+                Some((
+                    target,
+                    remaining,
+                    Route {
+                        open_valves: route.open_valves.with(target),
+                        pressure: route.pressure + self.flows[target] * remaining,
+                        openings,
+                    },
+                ))
+            }));
+
+            match best_routes.entry(route.open_valves.clone()) {
+                Entry::Occupied(mut entry) => {
+                    if route.pressure > entry.get().pressure {
+                        entry.insert(route);
+                    }
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(route);
+                }
+            }
+        }
 
-                Some(State {
-                    valve_id: target,
-                    remaining: state.remaining - path.len() as u64 - 1,
-                    pressure: state.pressure + reward,
-                    open_valves: state.open_valves | (1 << target as u64),
-                })
-            },
-        ));
+        best_routes
+            .into_values()
+            .sorted_unstable_by_key(|route| u64::MAX - route.pressure)
+            .collect()
     }
 
-    best_pressures
+    /// Compute the best plan for cooperating agents, each with its own time budget
+    fn plan(&self, time_budgets: &[u64]) -> Option<Plan<'a>> {
+        let (&last_budget, budgets) = time_budgets.split_last()?;
+
+        let mut agent_routes = budgets
+            .iter()
+            .map(|&budget| self.compute_routes(budget))
+            .collect_vec();
+
+        let mut combinations = vec![Combination {
+            open_valves: ValveSet::default(),
+            pressure: 0,
+            routes: Vec::new(),
+        }];
+
+        for routes in &agent_routes {
+            let mut best_combinations: HashMap<ValveSet, Combination> = HashMap::new();
+
+            for combination in &combinations {
+                for (index, route) in routes.iter().enumerate() {
+                    if !combination.open_valves.is_disjoint(&route.open_valves) {
+                        continue;
+                    }
+
+                    let pressure = combination.pressure + route.pressure;
+                    let open_valves = combination.open_valves.union(&route.open_valves);
+
+                    let entry = best_combinations.entry(open_valves.clone());
+                    if matches!(&entry, Entry::Occupied(entry) if entry.get().pressure >= pressure)
+                    {
+                        continue;
+                    }
+
+                    let mut routes = combination.routes.clone();
+                    routes.push(index);
+
+                    entry.insert_entry(Combination {
+                        open_valves,
+                        pressure,
+                        routes,
+                    });
+                }
+            }
+
+            combinations = best_combinations
+                .into_values()
+                .sorted_unstable_by_key(|combination| u64::MAX - combination.pressure)
+                .collect();
+        }
+
+        let last_routes = self.compute_routes(last_budget);
+
+        // Both lists are sorted by decreasing pressure, so the search can stop early
+        let mut best: Option<(u64, &Combination, usize)> = None;
+
+        let max_last_pressure = last_routes.first()?.pressure;
+
+        for combination in &combinations {
+            let max_pressure = combination.pressure + max_last_pressure;
+            if best.is_some_and(|(best_pressure, _, _)| max_pressure <= best_pressure) {
+                break;
+            }
+
+            for (index, route) in last_routes.iter().enumerate() {
+                let pressure = combination.pressure + route.pressure;
+                if best.is_some_and(|(best_pressure, _, _)| pressure <= best_pressure) {
+                    break;
+                }
+
+                if combination.open_valves.is_disjoint(&route.open_valves) {
+                    best = Some((pressure, combination, index));
+                }
+            }
+        }
+
+        let (pressure, combination, last_index) = best?;
+        let mut routes = combination.routes.clone();
+        routes.push(last_index);
+        agent_routes.push(last_routes);
+
+        let schedules = iter::zip(&agent_routes, routes)
+            .map(|(agent_routes, index)| {
+                (agent_routes[index].openings.iter())
+                    .map(|opening| (self.names[opening.valve], opening.minute))
+                    .collect()
+            })
+            .collect();
+
+        Some(Plan {
+            pressure,
+            schedules,
+        })
+    }
 }
 
 fn main() -> Result<()> {
@@ -75,69 +310,52 @@ fn main() -> Result<()> {
     let re =
         Regex::new(r#"(?m)^Valve (\w+) has flow rate=(\d+); tunnels? leads? to valves? (.+?)$"#)?;
 
-    let mut valves_map = HashMap::new();
-
     let valves: Vec<_> = re
         .captures_iter(&input)
-        .enumerate()
-        .map(|(id, cap)| {
+        .map(|cap| {
             let name = cap.get(1).value()?.as_str();
             let flow = cap[2].parse()?;
             let links = cap.get(3).value()?.as_str().split(", ").collect();
 
-            valves_map.insert(name, id);
-
-            Result::Ok(Valve {
-                id: id as u8,
-                name,
-                flow,
-                links,
-            })
+            Result::Ok(Valve { name, flow, links })
         })
         .try_collect()?;
 
-    ensure!(valves.len() <= u64::BITS as usize, "input too long");
-
-    let connections = valves
-        .iter()
-        .map(|start_valve| {
-            let mut connections = HashMap::from([(start_valve.id, (vec![], start_valve.flow))]);
-            let mut current = VecDeque::from([(start_valve.name, vec![])]);
+    if flag("--dot") {
+        let mut dot = Dot::graph();
 
-            while let Some((name, path)) = current.pop_front() {
-                for link in &valves[valves_map[name]].links {
-                    let valve = &valves[valves_map[link]];
+        for valve in &valves {
+            let label = format!("{}\nflow {}", valve.name, valve.flow);
+            let color = match (valve.name, valve.flow) {
+                (START_VALVE, _) => "gold",
+                (_, 0) => "white",
+                _ => "lightblue",
+            };
+            dot.node(
+                valve.name,
+                &[("label", &label), ("style", "filled"), ("fillcolor", color)],
+            );
 
-                    if let Entry::Vacant(entry) = connections.entry(valve.id) {
-                        let new_path = iter::chain(&path, &[valve.id]).copied().collect_vec();
-                        entry.insert((new_path.clone(), valve.flow));
-                        current.push_back((link, new_path));
-                    }
-                }
+            for &link in valve.links.iter().filter(|&&link| valve.name < link) {
+                dot.edge(valve.name, link, &[]);
             }
-            connections
-        })
-        .collect_vec();
-
-    let start_valve_id = valves.iter().position(|valve| valve.name == "AA").value()? as u8;
-
-    let result1 = *compute_best_pressures(&connections, start_valve_id, 30)
-        .values()
-        .max()
-        .value()?;
-
-    let result2 = compute_best_pressures(&connections, start_valve_id, 26)
-        .iter()
-        .tuple_combinations()
-        .filter(|&((&open_valves_1, _), (&open_valves_2, _))| {
-            let count1 = open_valves_1.count_ones();
-            let count2 = open_valves_2.count_ones();
-            let count = (open_valves_1 | open_valves_2).count_ones();
-            count == count1 + count2
-        })
-        .map(|((_, &pressure_1), (_, &pressure_2))| pressure_1 + pressure_2)
-        .max()
-        .value()?;
+        }
+
+        print!("{dot}");
+        return Ok(());
+    }
+
+    let network = Network::new(&valves, START_VALVE)?;
+
+    let plan1 = network.plan(&[30]).value()?;
+    let plan2 = network.plan(&[26, 26]).value()?;
+
+    if flag("--schedule") {
+        println!("{plan1}\n\n{plan2}\n");
+    }
+
+    let result1 = plan1.pressure;
+    let result2 = plan2.pressure;
 
     println!("{result1}");
     println!("{result2}");
@@ -1,95 +1,238 @@
 use aoc::*;
 
+use eyre::{bail, ensure};
 use itertools::{Itertools, izip};
 use regex::Regex;
+use smallvec::{SmallVec, smallvec};
 
+use std::fmt::Write;
 use std::iter;
 
+const DEFAULT_TARGET: &str = "geode";
+
+/// Robot owned at the beginning, which every blueprint must have
+const STARTING_ROBOT: &str = "ore";
+
+type Amounts = SmallVec<[u64; 4]>;
+
+#[derive(Clone)]
 struct State {
-    resources: [u64; 4],
-    robots: [u64; 4],
+    resources: Amounts,
+    robots: Amounts,
     remaining: u64,
 }
 
-impl State {
-    fn max_geodes(&self) -> u64 {
-        self.resources[3] + self.robots[3] * self.remaining
-    }
+/// Construction of a robot, started at the beginning of a minute and ready at its end
+#[derive(Clone, Copy)]
+struct Build {
+    minute: u64,
+    robot: usize,
 }
 
-struct Blueprint {
+struct Blueprint<'a> {
     id: u64,
-    costs: [[u64; 4]; 4],
-    max_robots: [u64; 4],
+    names: Vec<&'a str>,
+    costs: Vec<Amounts>,
+    max_robots: Amounts,
+    target: usize,
+    starting_robot: usize,
 }
 
-impl Blueprint {
-    fn max_geodes(&self, remaining: u64) -> u64 {
-        let mut max_geodes = 0;
+impl<'a> Blueprint<'a> {
+    /// Parse a blueprint, where each robot produces one unit per minute of the resource it is named after
+    fn parse(
+        id: u64,
+        text: &'a str,
+        target_name: &str,
+        re_robot: &Regex,
+        re_cost: &Regex,
+    ) -> Result<Self> {
+        let robots: Vec<_> = re_robot
+            .captures_iter(text)
+            .map(|cap| (cap.get(1).value().map(|x| x.as_str()), cap.get(2).value()))
+            .map(|(name, costs)| Result::Ok((name?, costs?.as_str())))
+            .try_collect()?;
 
-        let initial_state = State {
-            resources: [0; 4],
-            robots: [1, 0, 0, 0],
-            remaining,
-        };
+        let names = robots.iter().map(|&(name, _)| name).collect_vec();
+        ensure!(!names.is_empty(), "no robots in blueprint {id}");
+        ensure!(
+            names.iter().all_unique(),
+            "duplicate robot in blueprint {id}"
+        );
 
-        let mut current_states = vec![initial_state];
+        let costs: Vec<Amounts> = (robots.iter())
+            .map(|&(name, text)| {
+                let mut cost = smallvec![0; names.len()];
 
-        while let Some(state) = current_states.pop() {
-            current_states.extend((0..4).flat_map(|i_blueprint| {
-                if state.robots[i_blueprint] >= self.max_robots[i_blueprint] {
-                    return None;
+                for part in text.split(" and ") {
+                    let cap = re_cost.captures(part).value()?;
+                    let Some(index) = names.iter().position(|&x| x == &cap[2]) else {
+                        bail!("no robot produces {} for {name} robots", &cap[2]);
+                    };
+                    cost[index] += cap[1].parse::<u64>()?;
                 }
 
-                let cost = &self.costs[i_blueprint];
-
-                let wait_time = (0..4)
-                    .map(|i_resource| {
-                        if state.resources[i_resource] >= cost[i_resource] {
-                            0
-                        } else if state.robots[i_resource] == 0 {
-                            u64::MAX - 1
-                        } else {
-                            let diff = cost[i_resource] - state.resources[i_resource] - 1;
-                            diff / state.robots[i_resource] + 1
-                        }
-                    })
-                    .max()
-                    .unwrap_or_default();
-
-                let elasped = wait_time + 1;
-                if state.remaining <= elasped {
-                    return None;
-                }
+                Ok(cost)
+            })
+            .try_collect()?;
+
+        let Some(target) = names.iter().position(|&x| x == target_name) else {
+            bail!("no {target_name} robot in blueprint {id}");
+        };
+
+        let Some(starting_robot) = names.iter().position(|&x| x == STARTING_ROBOT) else {
+            bail!("no {STARTING_ROBOT} robot to start with in blueprint {id}");
+        };
+
+        let mut max_robots: Amounts = smallvec![0; names.len()];
+        for cost in &costs {
+            iter::zip(&mut max_robots, cost).for_each(|(max, &c)| *max = c.max(*max));
+        }
+        max_robots[target] = u64::MAX;
+
+        Ok(Self {
+            id,
+            names,
+            costs,
+            max_robots,
+            target,
+            starting_robot,
+        })
+    }
+
+    /// Robots owned at the beginning
+    fn initial_robots(&self) -> Amounts {
+        let mut robots: Amounts = smallvec![0; self.names.len()];
+        robots[self.starting_robot] = 1;
+        robots
+    }
+
+    /// Compute the robot to build next, with the state once it is built
+    fn next_states<'b>(&'b self, state: &'b State) -> impl Iterator<Item = (usize, State)> + 'b {
+        (0..self.names.len()).rev().filter_map(move |robot| {
+            if state.robots[robot] >= self.max_robots[robot] {
+                return None;
+            }
+
+            let wait_time = izip!(&self.costs[robot], &state.resources, &state.robots)
+                .map(|(&cost, &resource, &robots)| {
+                    if resource >= cost {
+                        Some(0)
+                    } else {
+                        (cost - resource - 1).checked_div(robots).map(|x| x + 1)
+                    }
+                })
+                .try_fold(0, |max, wait| wait.map(|x| x.max(max)))?;
+
+            let elapsed = wait_time + 1;
+            if state.remaining <= elapsed {
+                return None;
+            }
+
+            let resources = izip!(&state.resources, &state.robots, &self.costs[robot])
+                .map(|(resource, robots, cost)| resource + robots * elapsed - cost)
+                .collect();
+
+            let mut robots = state.robots.clone();
+            robots[robot] += 1;
+
+            let new_state = State {
+                resources,
+                robots,
+                remaining: state.remaining - elapsed,
+            };
+
+            Some((robot, new_state))
+        })
+    }
+
+    /// Amount of the target resource at the end if no more robots are built
+    fn final_amount(&self, state: &State) -> u64 {
+        state.resources[self.target] + state.robots[self.target] * state.remaining
+    }
+
+    /// Upper bound of the final amount of the target resource, as if a target robot was built every minute
+    fn upper_bound(&self, state: &State) -> u64 {
+        self.final_amount(state) + state.remaining * state.remaining.saturating_sub(1) / 2
+    }
+
+    /// Find a build schedule maximizing the final amount of the target resource, with branch and bound
+    fn optimize(&self, total_time: u64) -> (u64, Vec<Build>) {
+        let initial_state = State {
+            resources: smallvec![0; self.names.len()],
+            robots: self.initial_robots(),
+            remaining: total_time,
+        };
+
+        let mut best = (self.final_amount(&initial_state), Vec::new());
+        self.search(&initial_state, total_time, &mut Vec::new(), &mut best);
+        best
+    }
 
-                let new_remaining = state.remaining - elasped;
+    fn search(
+        &self,
+        state: &State,
+        total_time: u64,
+        builds: &mut Vec<Build>,
+        best: &mut (u64, Vec<Build>),
+    ) {
+        for (robot, new_state) in self.next_states(state) {
+            if self.upper_bound(&new_state) <= best.0 {
+                continue;
+            }
 
-                let mut new_resources = state.resources;
+            builds.push(Build {
+                minute: total_time - new_state.remaining,
+                robot,
+            });
 
-                izip!(&mut new_resources, &state.robots, cost)
-                    .for_each(|(resource, robot, c)| *resource = *resource + robot * elasped - c);
+            let amount = self.final_amount(&new_state);
+            if amount > best.0 {
+                *best = (amount, builds.clone());
+            }
+
+            self.search(&new_state, total_time, builds, best);
+            builds.pop();
+        }
+    }
 
-                let mut new_robots = state.robots;
-                new_robots[i_blueprint] += 1;
+    /// Render a build schedule minute by minute
+    fn render_schedule(&self, builds: &[Build], total_time: u64) -> Result<String> {
+        let mut output = String::new();
+        let mut resources: Amounts = smallvec![0; self.names.len()];
+        let mut robots = self.initial_robots();
 
-                let new_state = State {
-                    resources: new_resources,
-                    robots: new_robots,
-                    remaining: new_remaining,
-                };
+        let mut builds = builds.iter().peekable();
 
-                let new_max_geodes = new_state.max_geodes();
+        for minute in 1..=total_time {
+            let build = builds.next_if(|build| build.minute == minute);
 
-                if new_max_geodes + (new_remaining * (new_remaining - 1)) / 2 > max_geodes {
-                    max_geodes = max_geodes.max(new_max_geodes);
-                    Some(new_state)
-                } else {
-                    None
+            if let Some(build) = build {
+                for (resource, &cost) in iter::zip(&mut resources, &self.costs[build.robot]) {
+                    *resource = resource.checked_sub(cost).value()?;
                 }
-            }));
+            }
+
+            iter::zip(&mut resources, &robots).for_each(|(resource, robots)| *resource += robots);
+
+            if let Some(build) = build {
+                robots[build.robot] += 1;
+            }
+
+            let inventory = iter::zip(&self.names, &resources)
+                .filter(|&(_, &amount)| amount > 0)
+                .map(|(name, amount)| format!("{amount} {name}"))
+                .join(", ");
+
+            let action = match build {
+                Some(build) => format!("build {} robot", self.names[build.robot]),
+                None => "wait".to_owned(),
+            };
+
+            writeln!(output, "minute {minute:2}: {action:<20} [{inventory}]")?;
         }
 
-        max_geodes
+        Ok(output)
     }
 }
 
@@ -97,53 +240,53 @@ fn main() -> Result<()> {
     let input = setup(file!())?;
     let input = String::from_utf8_lossy(&input);
 
-    let re = Regex::new(concat!(
-        r#"(?m)^Blueprint (\d+): Each ore robot costs (\d+) ore. "#,
-        r#"Each clay robot costs (\d+) ore. "#,
-        r#"Each obsidian robot costs (\d+) ore and (\d+) clay. "#,
-        r#"Each geode robot costs (\d+) ore and (\d+) obsidian.$"#,
-    ))?;
-
-    let blueprints: Vec<_> = re
-        .captures_iter(&input)
-        .map(|cap| {
-            let id = cap[1].parse()?;
-            let ore_robot_ore_cost = cap[2].parse()?;
-            let clay_robot_ore_cost = cap[3].parse()?;
-            let obsidian_robot_ore_cost = cap[4].parse()?;
-            let obsidian_robot_clay_cost = cap[5].parse()?;
-            let geode_robot_ore_cost = cap[6].parse()?;
-            let geode_robot_obsidian_cost = cap[7].parse()?;
-
-            let costs = [
-                [ore_robot_ore_cost, 0, 0, 0],
-                [clay_robot_ore_cost, 0, 0, 0],
-                [obsidian_robot_ore_cost, obsidian_robot_clay_cost, 0, 0],
-                [geode_robot_ore_cost, 0, geode_robot_obsidian_cost, 0],
-            ];
-
-            let max_robots = (costs.iter()).fold([0, 0, 0, u64::MAX], |mut max_cost, cost| {
-                iter::zip(&mut max_cost, cost).for_each(|(max, &c)| *max = c.max(*max));
-                max_cost
-            });
+    let target: String = option("--target")?.unwrap_or_else(|| DEFAULT_TARGET.to_owned());
 
-            Result::Ok(Blueprint {
-                id,
-                costs,
-                max_robots,
-            })
+    let re = Regex::new(r#"Blueprint (\d+):"#)?;
+    let re_robot = Regex::new(r#"Each (\w+) robot costs ([^.]+)\."#)?;
+    let re_cost = Regex::new(r#"^(\d+) (\w+)$"#)?;
+
+    let headers = re.captures_iter(&input).collect_vec();
+
+    let blueprints: Vec<_> = (headers.iter())
+        .enumerate()
+        .map(|(index, cap)| {
+            let start = cap.get(0).value()?.end();
+            let end = headers
+                .get(index + 1)
+                .map_or(input.len(), |next| next.get(0).map_or(0, |x| x.start()));
+            Blueprint::parse(
+                cap[1].parse()?,
+                &input[start..end],
+                &target,
+                &re_robot,
+                &re_cost,
+            )
         })
         .try_collect()?;
 
+    if let Some(id) = option::<u64>("--schedule")? {
+        let blueprint = blueprints
+            .iter()
+            .find(|blueprint| blueprint.id == id)
+            .value()?;
+
+        for total_time in [24, 32] {
+            let (amount, builds) = blueprint.optimize(total_time);
+            println!("{}", blueprint.render_schedule(&builds, total_time)?);
+            println!("{amount} {target} in {total_time} minutes\n");
+        }
+    }
+
     let result1 = blueprints
         .iter()
-        .map(|blueprint| blueprint.id * blueprint.max_geodes(24))
+        .map(|blueprint| blueprint.id * blueprint.optimize(24).0)
         .sum::<u64>();
 
     let result2 = blueprints
         .iter()
         .take(3)
-        .map(|blueprint| blueprint.max_geodes(32))
+        .map(|blueprint| blueprint.optimize(32).0)
         .product::<u64>();
 
     println!("{result1}");
@@ -1,9 +1,11 @@
+use aoc::dag::{Dag, Evaluator};
+use aoc::rational::Rational;
 use aoc::*;
 
-use eyre::bail;
+use eyre::{bail, ensure};
 use itertools::Itertools;
 
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::HashMap;
 
 enum Operator {
     Add,
@@ -28,147 +30,174 @@ impl<'a> Monkey<'a> {
         }
     }
 
-    fn is_calculable(&self, values: &HashMap<&str, i64>) -> bool {
+    fn dependencies(&self) -> Option<[&'a str; 2]> {
         match *self {
-            Self::Constant(_) => true,
-            Self::Calculated(_, op1, op2) => values.contains_key(op1) && values.contains_key(op2),
+            Self::Constant(_) => None,
+            Self::Calculated(_, op1, op2) => Some([op1, op2]),
         }
     }
 }
 
-fn compute_root_value(
-    monkeys: &HashMap<&str, Monkey>,
-    inverted_graph: &HashMap<&str, Vec<&str>>,
-) -> i64 {
-    let mut values = HashMap::new();
-
-    let mut queue: VecDeque<_> = monkeys
-        .iter()
-        .filter(|&(_, monkey)| matches!(monkey, Monkey::Constant(_)))
-        .map(|(&name, _)| name)
-        .collect();
-
-    while let Some(name) = queue.pop_front() {
-        values.insert(name, monkeys[name].value(&values));
-        queue.extend(
-            inverted_graph[name]
-                .iter()
-                .copied()
-                .filter(|&x| monkeys[x].is_calculable(&values)),
-        );
+/// Linear expression `coefficient * x + constant` of a single variable `x`
+#[derive(Copy, Clone)]
+struct Linear {
+    coefficient: Rational,
+    constant: Rational,
+}
+
+impl Linear {
+    fn constant(constant: Rational) -> Self {
+        Self {
+            coefficient: Rational::ZERO,
+            constant,
+        }
     }
 
-    values["root"]
-}
+    fn variable() -> Self {
+        Self {
+            coefficient: Rational::ONE,
+            constant: Rational::ZERO,
+        }
+    }
 
-fn compute_human_value(
-    monkeys: &HashMap<&str, Monkey>,
-    inverted_graph: &HashMap<&str, Vec<&str>>,
-) -> Result<i64> {
-    let mut values = HashMap::new();
+    fn is_constant(self) -> bool {
+        self.coefficient.is_zero()
+    }
 
-    let mut human_dependencies = HashSet::new();
-    let mut queue: VecDeque<_> = inverted_graph["humn"].iter().copied().collect();
+    fn scale(self, factor: Rational) -> Result<Self> {
+        Ok(Self {
+            coefficient: self.coefficient.checked_mul(factor)?,
+            constant: self.constant.checked_mul(factor)?,
+        })
+    }
 
-    while let Some(name) = queue.pop_front() {
-        human_dependencies.insert(name);
-        queue.extend(inverted_graph[name].iter().copied());
+    fn add(self, other: Self) -> Result<Self> {
+        Ok(Self {
+            coefficient: self.coefficient.checked_add(other.coefficient)?,
+            constant: self.constant.checked_add(other.constant)?,
+        })
     }
 
-    queue.clear();
-
-    queue.extend(
-        monkeys
-            .iter()
-            .filter(|&(&name, monkey)| name != "humn" && matches!(monkey, Monkey::Constant(_)))
-            .map(|(&name, _)| name),
-    );
-
-    while let Some(name) = queue.pop_front() {
-        values.insert(name, monkeys[name].value(&values));
-        queue.extend(
-            inverted_graph[name].iter().copied().filter(|&x| {
-                monkeys[x].is_calculable(&values) && !human_dependencies.contains(name)
-            }),
-        );
+    fn sub(self, other: Self) -> Result<Self> {
+        Ok(Self {
+            coefficient: self.coefficient.checked_sub(other.coefficient)?,
+            constant: self.constant.checked_sub(other.constant)?,
+        })
     }
 
-    let (mut current_unknown, mut current_value) = match monkeys["root"] {
-        Monkey::Constant(_) => bail!("should not be a constant"),
-        Monkey::Calculated(_, op1, op2) => match (values.get(op1), values.get(op2)) {
-            (Some(&v1), None) => (op2, v1),
-            (None, Some(&v2)) => (op1, v2),
-            _ => bail!("values are incorrect"),
-        },
-    };
+    fn mul(self, other: Self) -> Result<Option<Self>> {
+        match (self.is_constant(), other.is_constant()) {
+            (_, true) => self.scale(other.constant).map(Some),
+            (true, false) => other.scale(self.constant).map(Some),
+            (false, false) => Ok(None),
+        }
+    }
 
-    loop {
-        match monkeys[current_unknown] {
-            Monkey::Constant(_) => bail!("should not be a constant"),
-            Monkey::Calculated(ref op, op1, op2) => match (values.get(op1), values.get(op2)) {
-                (Some(&v1), None) => {
-                    current_unknown = op2;
-                    current_value = match op {
-                        Operator::Add => current_value - v1,
-                        Operator::Sub => v1 - current_value,
-                        Operator::Mul => current_value / v1,
-                        Operator::Div => v1 / current_value,
-                    };
-                }
-                (None, Some(&v2)) => {
-                    current_unknown = op1;
-                    current_value = match op {
-                        Operator::Add => current_value - v2,
-                        Operator::Sub => current_value + v2,
-                        Operator::Mul => current_value / v2,
-                        Operator::Div => current_value * v2,
-                    };
+    fn div(self, other: Self) -> Result<Option<Self>> {
+        if other.is_constant() && !other.constant.is_zero() {
+            self.scale(other.constant.recip()?).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+fn compute_linear_expressions<'a>(
+    monkeys: &Dag<&'a str, Monkey<'a>>,
+    variable: &str,
+) -> Result<HashMap<&'a str, Linear>> {
+    let mut expressions = HashMap::<_, Linear>::new();
+
+    for name in monkeys.topological_order()? {
+        let expression = match *monkeys.node(name).value()? {
+            _ if name == variable => Linear::variable(),
+            Monkey::Constant(n) => Linear::constant(Rational::integer(n.into())),
+            Monkey::Calculated(ref op, op1, op2) => {
+                let (e1, e2) = (expressions[op1], expressions[op2]);
+
+                let expression = match op {
+                    Operator::Add => Some(e1.add(e2)?),
+                    Operator::Sub => Some(e1.sub(e2)?),
+                    Operator::Mul => e1.mul(e2)?,
+                    Operator::Div if e2.is_constant() && e2.constant.is_zero() => {
+                        bail!("division by zero in monkey {name}")
+                    }
+                    Operator::Div => e1.div(e2)?,
+                };
+
+                match expression {
+                    Some(expression) => expression,
+                    None => bail!("non-linear occurrence of {variable} in monkey {name}"),
                 }
-                _ => bail!("values are incorrect"),
-            },
+            }
         };
 
-        if current_unknown == "humn" {
-            break Ok(current_value);
+        expressions.insert(name, expression);
+    }
+
+    Ok(expressions)
+}
+
+/// Solve the equation `op1 = op2` of the root monkey for the given variable
+fn solve<'a>(
+    monkeys: &Dag<&'a str, Monkey<'a>>,
+    root: &'a str,
+    variable: &'a str,
+) -> Result<Rational> {
+    ensure!(monkeys.contains(variable), "unknown variable: {variable}");
+
+    let (op1, op2) = match *monkeys.node(root).value()? {
+        Monkey::Constant(_) => bail!("root monkey should not be a constant"),
+        Monkey::Calculated(_, op1, op2) => (op1, op2),
+    };
+
+    let expressions = compute_linear_expressions(monkeys, variable)?;
+    let difference = expressions[op1].sub(expressions[op2])?;
+
+    if difference.coefficient.is_zero() {
+        match difference.constant.is_zero() {
+            true => bail!("equation has infinitely many solutions for {variable}"),
+            false => bail!("equation has no solution for {variable}"),
         }
     }
+
+    (difference.constant.checked_neg()?).checked_div(difference.coefficient)
 }
 
 fn main() -> Result<()> {
     let input = setup(file!())?;
     let input = String::from_utf8_lossy(&input);
 
-    let mut inverted_graph = HashMap::<_, Vec<_>>::new();
-
-    let monkeys: HashMap<_, _> = input
-        .lines()
-        .map(|line| {
-            let (name, operation) = line.split(": ").next_tuple().value()?;
-
-            let monkey = match operation.parse::<i64>() {
-                Ok(n) => Monkey::Constant(n),
-                Err(_) => {
-                    let (op1, op, op2) = operation.split_ascii_whitespace().next_tuple().value()?;
-                    inverted_graph.entry(name).or_default();
-                    inverted_graph.entry(op1).or_default().push(name);
-                    inverted_graph.entry(op2).or_default().push(name);
-
-                    match op {
-                        "+" => Monkey::Calculated(Operator::Add, op1, op2),
-                        "-" => Monkey::Calculated(Operator::Sub, op1, op2),
-                        "*" => Monkey::Calculated(Operator::Mul, op1, op2),
-                        "/" => Monkey::Calculated(Operator::Div, op1, op2),
-                        _ => bail!("unknown operation: {op}"),
-                    }
+    let mut monkeys = Dag::new();
+
+    for line in input.lines() {
+        let (name, operation) = line.split(": ").next_tuple().value()?;
+
+        let monkey = match operation.parse::<i64>() {
+            Ok(n) => Monkey::Constant(n),
+            Err(_) => {
+                let (op1, op, op2) = operation.split_ascii_whitespace().next_tuple().value()?;
+
+                match op {
+                    "+" => Monkey::Calculated(Operator::Add, op1, op2),
+                    "-" => Monkey::Calculated(Operator::Sub, op1, op2),
+                    "*" => Monkey::Calculated(Operator::Mul, op1, op2),
+                    "/" => Monkey::Calculated(Operator::Div, op1, op2),
+                    _ => bail!("unknown operation: {op}"),
                 }
-            };
+            }
+        };
 
-            Ok((name, monkey))
-        })
-        .try_collect()?;
+        let dependencies = monkey.dependencies();
+        monkeys.insert(name, monkey, dependencies.into_iter().flatten());
+    }
 
-    let result1 = compute_root_value(&monkeys, &inverted_graph);
-    let result2 = compute_human_value(&monkeys, &inverted_graph)?;
+    let result1 = *Evaluator::new(&monkeys, Monkey::value).get("root")?;
+
+    let human_value = solve(&monkeys, "root", "humn")?;
+    let Some(result2) = human_value.to_integer() else {
+        bail!("non-integer solution: {human_value}");
+    };
 
     println!("{result1}");
     println!("{result2}");
@@ -1,3 +1,4 @@
+use aoc::rotation::{Mat3x3, Matrix, Vec3, Vector};
 use aoc::*;
 
 use eyre::{bail, ensure};
@@ -5,6 +6,7 @@ use itertools::Itertools;
 use num_complex::Complex;
 
 use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
 use std::iter;
 use std::ops::ControlFlow;
 
@@ -18,78 +20,6 @@ const ROTATION_RIGHT: &Mat3x3 = &[[0, 0, -1], [0, 1, 0], [1, 0, 0]];
 const ROTATION_DOWN: &Mat3x3 = &[[1, 0, 0], [0, 0, 1], [0, -1, 0]];
 const ROTATION_UP: &Mat3x3 = &[[1, 0, 0], [0, 0, -1], [0, 1, 0]];
 
-type Vec3 = [i64; 3];
-type Mat3x3 = [Vec3; 3];
-
-trait Vector {
-    fn sub(&self, rhs: &Self) -> Self;
-    fn dot(&self, rhs: &Self) -> i64;
-    fn apply(&self, matrix: &Mat3x3) -> Self;
-}
-
-impl Vector for Vec3 {
-    fn sub(&self, rhs: &Self) -> Self {
-        [self[0] - rhs[0], self[1] - rhs[1], self[2] - rhs[2]]
-    }
-
-    fn dot(&self, rhs: &Self) -> i64 {
-        self[0] * rhs[0] + self[1] * rhs[1] + self[2] * rhs[2]
-    }
-
-    fn apply(&self, matrix: &Mat3x3) -> Self {
-        [
-            self.dot(&matrix[0]),
-            self.dot(&matrix[1]),
-            self.dot(&matrix[2]),
-        ]
-    }
-}
-
-trait Matrix {
-    fn identity() -> Self;
-    fn matmul(&self, rhs: &Self) -> Self;
-    fn transpose(&self) -> Self;
-    fn z_axis(&self) -> Vec3;
-}
-
-impl Matrix for Mat3x3 {
-    fn identity() -> Self {
-        [[1, 0, 0], [0, 1, 0], [0, 0, 1]]
-    }
-
-    fn matmul(&self, rhs: &Mat3x3) -> Mat3x3 {
-        [
-            [
-                self[0][0] * rhs[0][0] + self[0][1] * rhs[1][0] + self[0][2] * rhs[2][0],
-                self[0][0] * rhs[0][1] + self[0][1] * rhs[1][1] + self[0][2] * rhs[2][1],
-                self[0][0] * rhs[0][2] + self[0][1] * rhs[1][2] + self[0][2] * rhs[2][2],
-            ],
-            [
-                self[1][0] * rhs[0][0] + self[1][1] * rhs[1][0] + self[1][2] * rhs[2][0],
-                self[1][0] * rhs[0][1] + self[1][1] * rhs[1][1] + self[1][2] * rhs[2][1],
-                self[1][0] * rhs[0][2] + self[1][1] * rhs[1][2] + self[1][2] * rhs[2][2],
-            ],
-            [
-                self[2][0] * rhs[0][0] + self[2][1] * rhs[1][0] + self[2][2] * rhs[2][0],
-                self[2][0] * rhs[0][1] + self[2][1] * rhs[1][1] + self[2][2] * rhs[2][1],
-                self[2][0] * rhs[0][2] + self[2][1] * rhs[1][2] + self[2][2] * rhs[2][2],
-            ],
-        ]
-    }
-
-    fn transpose(&self) -> Self {
-        [
-            [self[0][0], self[1][0], self[2][0]],
-            [self[0][1], self[1][1], self[2][1]],
-            [self[0][2], self[1][2], self[2][2]],
-        ]
-    }
-
-    fn z_axis(&self) -> Vec3 {
-        [self[0][2], self[1][2], self[2][2]]
-    }
-}
-
 #[derive(Copy, Clone, Eq, PartialEq)]
 enum Tile {
     Empty,
@@ -150,6 +80,12 @@ impl BlockGrid {
     }
 
     fn compute_orientations(&mut self) -> Result<HashMap<Vec3, usize>> {
+        let face_count = self.grids.iter().flatten().count();
+        ensure!(
+            face_count == 6,
+            "cube net should have 6 faces, found {face_count}"
+        );
+
         let initial_column = self
             .grids
             .iter()
@@ -161,6 +97,7 @@ impl BlockGrid {
 
         let mut visited = HashSet::from([initial_position]);
         let mut queue = vec![(initial_position, initial_orientation)];
+        let mut visited_face_count = 1;
 
         while let Some(((row, column), orientation)) = queue.pop() {
             queue.extend(
@@ -176,6 +113,7 @@ impl BlockGrid {
                 .flat_map(|((new_row, new_column), rotation)| {
                     let new_index = self.get_index(new_row, new_column);
                     self.grids[new_index].as_mut().map(|grid| {
+                        visited_face_count += 1;
                         grid.orientation = orientation.matmul(rotation);
                         ((new_row, new_column), grid.orientation)
                     })
@@ -183,12 +121,108 @@ impl BlockGrid {
             );
         }
 
-        Ok(self
+        ensure!(
+            visited_face_count == face_count,
+            "cube net should be connected"
+        );
+
+        let faces: HashMap<_, _> = self
             .grids
             .iter()
             .enumerate()
-            .flat_map(|(index, grid)| grid.as_ref().map(|grid| (grid.orientation.z_axis(), index)))
-            .collect())
+            .flat_map(|(index, grid)| {
+                grid.as_ref()
+                    .map(|grid| (grid.orientation.column(2), index))
+            })
+            .collect();
+
+        ensure!(faces.len() == face_count, "net does not fold into a cube");
+
+        Ok(faces)
+    }
+
+    /// Compute the face reached when leaving a face in the given direction,
+    /// and the rotation from the coordinates of the current face to the coordinates of the new face
+    fn transition(
+        &self,
+        faces: &HashMap<Vec3, usize>,
+        block_index: usize,
+        direction: Complex<i64>,
+    ) -> Result<(usize, Mat3x3)> {
+        let rotation = match direction {
+            LEFT => ROTATION_LEFT,
+            RIGHT => ROTATION_RIGHT,
+            DOWN => ROTATION_DOWN,
+            UP => ROTATION_UP,
+            direction => bail!("incorrect direction: {direction}"),
+        };
+
+        let grid = self.grids[block_index].as_ref().value()?;
+        let rotated = grid.orientation.matmul(rotation);
+        let new_block_index = *faces.get(&rotated.column(2)).value()?;
+        let new_grid = self.grids[new_block_index].as_ref().value()?;
+
+        let coord_rotation = new_grid.orientation.transpose().matmul(&rotated);
+
+        ensure!(
+            coord_rotation[2] == [0, 0, 1] && coord_rotation.column(2) == [0, 0, 1],
+            "should be a 2D rotation"
+        );
+
+        Ok((new_block_index, coord_rotation))
+    }
+
+    /// Render the cube net with numbered faces, followed by the adjacency of each face on the folded cube
+    fn render_faces(&self, faces: &HashMap<Vec3, usize>) -> Result<String> {
+        let face_numbers: HashMap<_, _> = (self.grids.iter().enumerate())
+            .filter(|(_, grid)| grid.is_some())
+            .enumerate()
+            .map(|(number, (index, _))| (index, number + 1))
+            .collect();
+
+        let mut output = String::new();
+
+        for row in 0..self.height {
+            let line: String = (0..self.width)
+                .map(
+                    |column| match face_numbers.get(&self.get_index(row, column)) {
+                        Some(&number) => char::from_digit(number as u32, 10).unwrap_or('?'),
+                        None => ' ',
+                    },
+                )
+                .collect();
+
+            writeln!(output, "{}", line.trim_end())?;
+        }
+
+        writeln!(output)?;
+
+        for (index, number) in face_numbers
+            .iter()
+            .sorted_unstable_by_key(|&(_, &number)| number)
+        {
+            write!(output, "face {number}:")?;
+
+            for (i_direction, direction) in [RIGHT, DOWN, LEFT, UP].into_iter().enumerate() {
+                let (new_index, coord_rotation) = self.transition(faces, *index, direction)?;
+
+                let new_direction_vec = [direction.re, direction.im, 0].apply(&coord_rotation);
+                let entry_edge = -Complex::new(new_direction_vec[0], new_direction_vec[1]);
+
+                write!(
+                    output,
+                    "{} {} -> {} ({} edge)",
+                    if i_direction == 0 { "" } else { "," },
+                    direction_name(&direction)?,
+                    face_numbers[&new_index],
+                    direction_name(&entry_edge)?,
+                )?;
+            }
+
+            writeln!(output)?;
+        }
+
+        Ok(output)
     }
 }
 
@@ -329,25 +363,11 @@ impl<'a> State<'a> {
         faces: &HashMap<Vec3, usize>,
         wrapped_coord: Complex<i64>,
     ) -> Result<ControlFlow<()>> {
-        let rotation = match self.direction {
-            LEFT => ROTATION_LEFT,
-            RIGHT => ROTATION_RIGHT,
-            DOWN => ROTATION_DOWN,
-            UP => ROTATION_UP,
-            direction => bail!("incorrect direction: {direction}"),
-        };
+        let (new_block_index, coord_rotation) =
+            blocks.transition(faces, self.block_index, self.direction)?;
 
-        let rotated = self.grid.orientation.matmul(rotation);
-        let new_block_index = faces[&rotated.z_axis()];
         let new_grid = blocks.grids[new_block_index].as_ref().value()?;
 
-        let coord_rotation = new_grid.orientation.transpose().matmul(&rotated);
-
-        ensure!(
-            coord_rotation[2] == [0, 0, 1] && coord_rotation.z_axis() == [0, 0, 1],
-            "should be a 2D rotation"
-        );
-
         let new_coord_vec = [wrapped_coord.re, wrapped_coord.im, 0].apply(&coord_rotation);
         let new_origin_corner_vec = [-1, 1, 0].apply(&coord_rotation).sub(&[-1, 1, 0]);
 
@@ -407,6 +427,49 @@ fn direction_score(direction: &Complex<i64>) -> Result<i64> {
     }
 }
 
+fn direction_name(direction: &Complex<i64>) -> Result<&'static str> {
+    match *direction {
+        RIGHT => Ok("right"),
+        DOWN => Ok("down"),
+        LEFT => Ok("left"),
+        UP => Ok("up"),
+        _ => bail!("incorrect direction: {direction}"),
+    }
+}
+
+fn gcd(mut x: usize, mut y: usize) -> usize {
+    while y != 0 {
+        (x, y) = (y, x % y);
+    }
+    x
+}
+
+/// Compute the face size as the largest size compatible with all the borders of the net
+fn compute_face_size(map_lines: &[&str], map_width: usize) -> usize {
+    let is_filled = |row: usize, column: usize| {
+        (map_lines.get(row))
+            .is_some_and(|line| line.as_bytes().get(column).is_some_and(|&x| x != b' '))
+    };
+
+    let mut face_size = 0;
+
+    for row in 0..=map_lines.len() {
+        for column in 0..=map_width {
+            let filled = is_filled(row, column);
+
+            if column > 0 && filled != is_filled(row, column - 1) {
+                face_size = gcd(face_size, column);
+            }
+
+            if row > 0 && filled != is_filled(row - 1, column) {
+                face_size = gcd(face_size, row);
+            }
+        }
+    }
+
+    face_size
+}
+
 fn parse_input(input: &str) -> Result<(BlockGrid, HashMap<Vec3, usize>, Vec<Instruction>)> {
     let mut input_iter = input.split("\n\n");
 
@@ -418,38 +481,52 @@ fn parse_input(input: &str) -> Result<(BlockGrid, HashMap<Vec3, usize>, Vec<Inst
         .filter(|x| matches!(x, b'.' | b'#'))
         .count();
 
-    let block_size = ((tile_count / 6) as f64).sqrt() as usize;
-
     let map_width = map_lines.iter().map(|line| line.len()).max().value()?;
     let map_height = map_lines.len();
 
-    let block_grid_width = map_width / block_size;
-    let block_grid_height = map_height / block_size;
+    let block_size = compute_face_size(&map_lines, map_width);
+
+    ensure!(
+        block_size > 0 && tile_count == 6 * block_size * block_size,
+        "map should be a cube net with 6 square faces"
+    );
+
+    let block_grid_width = map_width.div_ceil(block_size);
+    let block_grid_height = map_height.div_ceil(block_size);
 
     let mut grids = vec![None; block_grid_width * block_grid_height];
 
     for (grid_chunk, line_chunk) in iter::zip(
         grids.chunks_exact_mut(block_grid_width),
-        map_lines.chunks_exact(block_size),
+        map_lines.chunks(block_size),
     ) {
         for line in line_chunk {
             for (grid, byte_chunk) in
-                iter::zip(&mut *grid_chunk, line.as_bytes().chunks_exact(block_size))
+                iter::zip(&mut *grid_chunk, line.as_bytes().chunks(block_size))
             {
-                if byte_chunk.first() != Some(&b' ') {
+                if byte_chunk.iter().any(|&x| x != b' ') {
                     let grid = grid.get_or_insert_with(|| Grid {
                         size: block_size,
                         orientation: Mat3x3::identity(),
                         tiles: Vec::with_capacity(block_size * block_size),
                     });
 
-                    grid.tiles
-                        .extend(byte_chunk.iter().copied().flat_map(Tile::from_ascii));
+                    for &x in byte_chunk {
+                        match Tile::from_ascii(x) {
+                            Some(tile) => grid.tiles.push(tile),
+                            None => bail!("invalid tile: {}", x as char),
+                        }
+                    }
                 }
             }
         }
     }
 
+    ensure!(
+        (grids.iter().flatten()).all(|grid| grid.tiles.len() == block_size * block_size),
+        "cube faces should be complete squares"
+    );
+
     let mut blocks = BlockGrid {
         width: block_grid_width,
         height: block_grid_height,
@@ -485,6 +562,10 @@ fn main() -> Result<()> {
 
     let (blocks, faces, instructions) = parse_input(&input)?;
 
+    if flag("--faces") {
+        println!("{}", blocks.render_faces(&faces)?);
+    }
+
     let result1 = compute_password(&blocks, &faces, &instructions, false)?;
     let result2 = compute_password(&blocks, &faces, &instructions, true)?;
 
@@ -4,7 +4,15 @@ use eyre::bail;
 use itertools::Itertools;
 use regex::Regex;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Display};
+use std::iter;
+
+const RATING_NAMES: [&str; 4] = ["x", "m", "a", "s"];
+const RATING_RANGE: (i64, i64) = (1, 4000);
+
+/// Set of ratings with an inclusive range for each category
+type Region = [(i64, i64); 4];
 
 #[derive(Copy, Clone)]
 enum Rule<'a> {
@@ -25,12 +33,59 @@ impl<'a> From<&'a str> for Rule<'a> {
     }
 }
 
+impl Display for Rule<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Accepted => f.write_str("A"),
+            Self::Rejected => f.write_str("R"),
+            Self::Workflow(name) => f.write_str(name),
+        }
+    }
+}
+
 #[derive(Copy, Clone)]
 enum Condition {
     Less(u8, i64),
     Greater(u8, i64),
 }
 
+impl Condition {
+    /// Split a region into its parts matching and not matching the condition
+    fn split(&self, region: Region) -> (Option<Region>, Option<Region>) {
+        let (index, matching_range, remaining_range) = match *self {
+            Condition::Less(index, value) => {
+                let (min, max) = region[index as usize];
+                (index, (min, max.min(value - 1)), (min.max(value), max))
+            }
+            Condition::Greater(index, value) => {
+                let (min, max) = region[index as usize];
+                (index, (min.max(value + 1), max), (min, max.min(value)))
+            }
+        };
+
+        let with_range = |(min, max): (i64, i64)| {
+            (min <= max).then(|| {
+                let mut new_region = region;
+                new_region[index as usize] = (min, max);
+                new_region
+            })
+        };
+
+        (with_range(matching_range), with_range(remaining_range))
+    }
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Condition::Less(index, value) => write!(f, "{}<{value}", RATING_NAMES[index as usize]),
+            Condition::Greater(index, value) => {
+                write!(f, "{}>{value}", RATING_NAMES[index as usize])
+            }
+        }
+    }
+}
+
 #[derive(Copy, Clone)]
 struct RuleWithCondition<'a> {
     condition: Option<Condition>,
@@ -59,6 +114,14 @@ impl<'a> RuleWithCondition<'a> {
         }
     }
 
+    /// Split a region into its parts matching and not matching the rule
+    fn split(&self, region: Region) -> (Option<Region>, Option<Region>) {
+        match self.condition {
+            None => (Some(region), None),
+            Some(condition) => condition.split(region),
+        }
+    }
+
     fn evaluate(&self, rating: &[i64; 4]) -> Option<Rule<'a>> {
         match self.condition {
             None => Some(self.rule),
@@ -72,6 +135,163 @@ impl<'a> RuleWithCondition<'a> {
     }
 }
 
+impl Display for RuleWithCondition<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.condition {
+            None => write!(f, "{}", self.rule),
+            Some(condition) => write!(f, "{condition}:{}", self.rule),
+        }
+    }
+}
+
+/// Result of the propagation of all possible ratings through the workflows
+struct Analysis<'a> {
+    accepting_regions: Vec<Region>,
+    entered_workflows: HashSet<&'a str>,
+    reached_rules: HashSet<(&'a str, usize)>,
+    diagnostics: Vec<String>,
+}
+
+impl<'a> Analysis<'a> {
+    fn new(workflows: &HashMap<&'a str, Vec<RuleWithCondition<'a>>>) -> Self {
+        let mut accepting_regions = Vec::new();
+        let mut entered_workflows = HashSet::new();
+        let mut reached_rules = HashSet::new();
+        let mut diagnostics = Vec::new();
+
+        let mut current_states = vec![("in", [RATING_RANGE; 4], 0)];
+
+        while let Some((workflow_name, region, depth)) = current_states.pop() {
+            let Some(rules) = workflows.get(workflow_name) else {
+                diagnostics.push(format!("unknown workflow: {workflow_name}"));
+                continue;
+            };
+
+            if depth > workflows.len() {
+                diagnostics.push(format!("workflow cycle through {workflow_name}"));
+                continue;
+            }
+
+            entered_workflows.insert(workflow_name);
+
+            let mut remaining = Some(region);
+
+            for (i_rule, rule) in rules.iter().enumerate() {
+                let Some(region) = remaining else { break };
+
+                let (matching, new_remaining) = rule.split(region);
+                remaining = new_remaining;
+
+                if let Some(matching) = matching {
+                    reached_rules.insert((workflow_name, i_rule));
+
+                    match rule.rule {
+                        Rule::Accepted => accepting_regions.push(matching),
+                        Rule::Rejected => (),
+                        Rule::Workflow(name) => current_states.push((name, matching, depth + 1)),
+                    }
+                }
+            }
+
+            if let Some(region) = remaining {
+                diagnostics.push(format!(
+                    "workflow {workflow_name} has no matching rule for {}",
+                    format_region(&region)
+                ));
+            }
+        }
+
+        Self {
+            accepting_regions,
+            entered_workflows,
+            reached_rules,
+            diagnostics,
+        }
+    }
+
+    fn combination_count(&self) -> i64 {
+        (self.accepting_regions.iter())
+            .map(|region| (region.iter().map(|(min, max)| max - min + 1)).product::<i64>())
+            .sum()
+    }
+
+    /// Report unreachable workflows and rules, and shadowed or overlapping conditions
+    fn lint(&self, workflows: &HashMap<&'a str, Vec<RuleWithCondition<'a>>>) -> Vec<String> {
+        let mut diagnostics = self.diagnostics.clone();
+
+        for (&workflow_name, rules) in workflows.iter().sorted_unstable_by_key(|&(name, _)| name) {
+            if !self.entered_workflows.contains(workflow_name) {
+                diagnostics.push(format!("workflow {workflow_name} can never be entered"));
+            }
+
+            let mut remaining = Some([RATING_RANGE; 4]);
+
+            for (i_rule, rule) in rules.iter().enumerate() {
+                let Some(region) = remaining else {
+                    diagnostics.push(format!(
+                        "rule {rule} of workflow {workflow_name} is shadowed by previous conditions"
+                    ));
+                    continue;
+                };
+
+                let (matching, new_remaining) = rule.split(region);
+                remaining = new_remaining;
+
+                let Some(matching) = matching else {
+                    diagnostics.push(format!(
+                        "rule {rule} of workflow {workflow_name} is shadowed by previous conditions"
+                    ));
+                    continue;
+                };
+
+                if self.entered_workflows.contains(workflow_name)
+                    && !self.reached_rules.contains(&(workflow_name, i_rule))
+                {
+                    diagnostics.push(format!(
+                        "rule {rule} of workflow {workflow_name} is unreachable"
+                    ));
+                }
+
+                if let Some(condition) = rule.condition {
+                    let (full_matching, _) = condition.split([RATING_RANGE; 4]);
+
+                    if full_matching.is_some_and(|full_matching| full_matching != matching) {
+                        let overlapping = rules[..i_rule]
+                            .iter()
+                            .flat_map(|previous| previous.condition)
+                            .filter(|previous| {
+                                full_matching
+                                    .is_some_and(|region| previous.split(region).0.is_some())
+                                    && condition_index(previous) == condition_index(&condition)
+                            })
+                            .join(", ");
+
+                        if !overlapping.is_empty() {
+                            diagnostics.push(format!(
+                                "condition {condition} of workflow {workflow_name} overlaps previous conditions: {overlapping}"
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+
+        diagnostics
+    }
+}
+
+fn condition_index(condition: &Condition) -> u8 {
+    match *condition {
+        Condition::Less(index, _) | Condition::Greater(index, _) => index,
+    }
+}
+
+fn format_region(region: &Region) -> String {
+    iter::zip(RATING_NAMES, region)
+        .map(|(name, (min, max))| format!("{name}={min}..={max}"))
+        .join(" ")
+}
+
 fn rating_index(name: &str) -> Result<u8> {
     match name {
         "x" => Ok(0),
@@ -132,51 +352,21 @@ fn main() -> Result<()> {
         })
         .try_sum::<i64>()?;
 
-    let mut possible_paths = Vec::new();
-    let mut current_states = vec![("in", Vec::new())];
-
-    while let Some((workflow_name, mut path)) = current_states.pop() {
-        for rule in &workflows[workflow_name] {
-            match rule.rule {
-                Rule::Rejected => (),
-                Rule::Accepted => {
-                    let new_path = path.iter().copied().chain(rule.condition).collect_vec();
-                    possible_paths.push(new_path);
-                }
-                Rule::Workflow(name) => {
-                    let new_path = path.iter().copied().chain(rule.condition).collect_vec();
-                    current_states.push((name, new_path))
-                }
-            }
+    let analysis = Analysis::new(&workflows);
 
-            path.extend(rule.condition.map(|condition| match condition {
-                Condition::Less(index, value) => Condition::Greater(index, value - 1),
-                Condition::Greater(index, value) => Condition::Less(index, value + 1),
-            }));
+    if flag("--regions") {
+        for region in &analysis.accepting_regions {
+            println!("{}", format_region(region));
         }
     }
 
-    let result2 = possible_paths
-        .iter()
-        .map(|path| {
-            let mut rating_ranges = [(1, 4000); 4];
-
-            for &condition in path {
-                match condition {
-                    Condition::Less(index, value) => {
-                        let max = &mut rating_ranges[index as usize].1;
-                        *max = (value - 1).min(*max);
-                    }
-                    Condition::Greater(index, value) => {
-                        let min = &mut rating_ranges[index as usize].0;
-                        *min = (value + 1).max(*min);
-                    }
-                }
-            }
+    if flag("--lint") {
+        for diagnostic in analysis.lint(&workflows) {
+            println!("{diagnostic}");
+        }
+    }
 
-            (rating_ranges.iter().map(|(min, max)| max - min + 1)).product::<i64>()
-        })
-        .sum::<i64>();
+    let result2 = analysis.combination_count();
 
     println!("{result1}");
     println!("{result2}");
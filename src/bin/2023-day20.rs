@@ -1,175 +1,460 @@
+use aoc::dot::Dot;
 use aoc::*;
 
-use eyre::{bail, ensure};
+use eyre::{WrapErr, bail, ensure, eyre};
 use itertools::Itertools;
 use regex::Regex;
-use smallvec::SmallVec;
 
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 
-#[derive(Copy, Clone)]
+const BROADCASTER: &str = "broadcaster";
+const SINK: &str = "rx";
+const PRESSES: u64 = 1000;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 enum Pulse {
     Low = 0,
     High = 1,
 }
 
-enum ModuleState<'a> {
-    None,
-    FlipFlop(bool),
-    Conjunction(HashMap<&'a str, Pulse>),
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum ModuleKind {
+    Broadcaster,
+    FlipFlop,
+    Conjunction,
 }
 
 struct Module<'a> {
-    state: ModuleState<'a>,
+    kind: ModuleKind,
     destinations: Vec<&'a str>,
 }
 
-fn gcd(mut x: u64, mut y: u64) -> u64 {
-    while y != 0 {
-        (x, y) = (y, x % y);
-    }
-    x
+struct Circuit<'a> {
+    modules: HashMap<&'a str, Module<'a>>,
+    inputs: HashMap<&'a str, Vec<&'a str>>,
 }
 
-fn lcm(x: u64, y: u64) -> u64 {
-    x * y / gcd(x, y)
-}
+impl<'a> Circuit<'a> {
+    fn parse(input: &'a str) -> Result<Self> {
+        let re = Regex::new(r#"(?m)^([%&]?)(\w+) -> (.+)$"#)?;
 
-fn main() -> Result<()> {
-    let input = setup(file!())?;
-    let input = String::from_utf8_lossy(&input);
+        let mut modules = HashMap::new();
+        let mut inputs = HashMap::<_, Vec<_>>::new();
 
-    let re = Regex::new(r#"(?m)^([%&]?)(\w+) -> (.+)$"#)?;
+        for cap in re.captures_iter(input) {
+            let kind = match &cap[1] {
+                "" => ModuleKind::Broadcaster,
+                "%" => ModuleKind::FlipFlop,
+                "&" => ModuleKind::Conjunction,
+                _ => bail!("invalid prefix"),
+            };
 
-    let mut graph = HashMap::new();
-    let mut inverted_graph = HashMap::<_, Vec<_>>::new();
+            let name = cap.get(2).value()?.as_str();
+            let destinations = cap.get(3).value()?.as_str().split(", ").collect_vec();
 
-    for cap in re.captures_iter(&input) {
-        let state = match &cap[1] {
-            "" => ModuleState::None,
-            "%" => ModuleState::FlipFlop(false),
-            "&" => ModuleState::Conjunction(HashMap::new()),
-            _ => bail!("invalid prefix"),
-        };
+            inputs.entry(name).or_default();
+            for &destination in &destinations {
+                inputs.entry(destination).or_default().push(name);
+            }
 
-        let name = cap.get(2).value()?.as_str();
-        let destinations = cap.get(3).value()?.as_str().split(", ").collect_vec();
+            modules.insert(name, Module { kind, destinations });
+        }
+
+        ensure!(
+            modules.contains_key(BROADCASTER),
+            "missing {BROADCASTER} module"
+        );
 
-        let module = Module {
-            state,
-            destinations: destinations.clone(),
-        };
+        Ok(Self { modules, inputs })
+    }
 
-        graph.insert(name, module);
-        inverted_graph.entry(name).or_default();
+    fn kind(&self, name: &str) -> Option<ModuleKind> {
+        self.modules.get(name).map(|module| module.kind)
+    }
 
-        for destination in destinations {
-            inverted_graph.entry(destination).or_default().push(name);
+    fn destinations(&self, name: &str) -> &[&'a str] {
+        self.modules
+            .get(name)
+            .map_or(&[], |module| &module.destinations)
+    }
+
+    fn inputs(&self, name: &str) -> &[&'a str] {
+        self.inputs.get(name).map_or(&[], |inputs| inputs)
+    }
+
+    fn to_dot(&self) -> Dot {
+        let mut dot = Dot::digraph();
+
+        for (name, module) in (self.modules.iter()).sorted_unstable_by_key(|&(&name, _)| name) {
+            let (label, shape) = match module.kind {
+                ModuleKind::Broadcaster => (name.to_string(), "doublecircle"),
+                ModuleKind::FlipFlop => (format!("%{name}"), "box"),
+                ModuleKind::Conjunction => (format!("&{name}"), "diamond"),
+            };
+            dot.node(name, &[("label", &label), ("shape", shape)]);
+
+            for destination in &module.destinations {
+                dot.edge(name, destination, &[]);
+            }
         }
+
+        dot
     }
+}
+
+struct Simulator<'c, 'a> {
+    circuit: &'c Circuit<'a>,
+    flip_flops: HashMap<&'a str, bool>,
+    memories: HashMap<&'a str, HashMap<&'a str, Pulse>>,
+    queue: VecDeque<(&'a str, Pulse, &'a str)>,
+}
+
+impl<'c, 'a> Simulator<'c, 'a> {
+    fn new(circuit: &'c Circuit<'a>) -> Self {
+        let memories = (circuit.modules.iter())
+            .filter(|(_, module)| module.kind == ModuleKind::Conjunction)
+            .map(|(&name, _)| {
+                let memory = (circuit.inputs(name).iter())
+                    .map(|&input| (input, Pulse::Low))
+                    .collect();
+                (name, memory)
+            })
+            .collect();
+
+        Self {
+            circuit,
+            flip_flops: HashMap::new(),
+            memories,
+            queue: VecDeque::new(),
+        }
+    }
+
+    /// Push the button, calling `on_pulse` with each `(source, pulse, destination)` in order
+    fn press(&mut self, mut on_pulse: impl FnMut(&'a str, Pulse, &'a str)) {
+        self.queue.clear();
+        self.queue.push_back(("button", Pulse::Low, BROADCASTER));
+
+        while let Some((source, pulse, name)) = self.queue.pop_front() {
+            on_pulse(source, pulse, name);
 
-    for (name, module) in &mut graph {
-        if let ModuleState::Conjunction(memory) = &mut module.state {
-            *memory = inverted_graph[name]
-                .iter()
-                .map(|&input_name| (input_name, Pulse::Low))
-                .collect();
+            let Some(module) = self.circuit.modules.get(name) else {
+                continue;
+            };
+
+            let output = match module.kind {
+                ModuleKind::Broadcaster => pulse,
+                ModuleKind::FlipFlop => {
+                    if pulse == Pulse::High {
+                        continue;
+                    }
+                    let state = self.flip_flops.entry(name).or_default();
+                    *state = !*state;
+                    if *state { Pulse::High } else { Pulse::Low }
+                }
+                ModuleKind::Conjunction => {
+                    let memory = self.memories.entry(name).or_default();
+                    memory.insert(source, pulse);
+                    if memory.values().all(|&pulse| pulse == Pulse::High) {
+                        Pulse::Low
+                    } else {
+                        Pulse::High
+                    }
+                }
+            };
+
+            (self.queue).extend(module.destinations.iter().map(|&x| (name, output, x)));
         }
     }
+}
 
-    let rx_inputs = &inverted_graph["rx"];
-    ensure!(rx_inputs.len() == 1, "unsupported input");
+/// Chain of flip-flops acting as a binary counter, reset by a conjunction when it reaches its period
+struct Counter<'a> {
+    bits: Vec<&'a str>,
+    hub: &'a str,
+    /// Single-input conjunctions between the hub and the collector
+    inverters: Vec<&'a str>,
+    period: u64,
+}
 
-    let rx_input = &graph[rx_inputs[0]];
+impl Counter<'_> {
+    fn modules(&self) -> impl Iterator<Item = &str> {
+        (self.bits.iter().chain([&self.hub]).chain(&self.inverters)).copied()
+    }
+}
+
+/// Counters sending a high pulse to the collector at each multiple of their period,
+/// where the collector sends a low pulse to the sink when all its inputs are high
+struct Analysis<'a> {
+    collector: &'a str,
+    counters: Vec<Counter<'a>>,
+}
 
+fn analyze_counter<'a>(
+    circuit: &Circuit<'a>,
+    head: &'a str,
+    collector: &'a str,
+) -> Result<Counter<'a>> {
     ensure!(
-        matches!(rx_input.state, ModuleState::Conjunction(_)),
-        "unsupported input"
+        circuit.kind(head) == Some(ModuleKind::FlipFlop),
+        "{BROADCASTER} output {head} is not a flip-flop"
     );
 
-    let mut cycles: HashMap<_, SmallVec<[u64; 2]>> = inverted_graph[rx_inputs[0]]
-        .iter()
-        .map(|&name| (name, SmallVec::new()))
-        .collect();
+    let hub = (circuit.destinations(head).iter())
+        .copied()
+        .filter(|&x| circuit.kind(x) == Some(ModuleKind::Conjunction))
+        .exactly_one()
+        .map_err(|_| eyre!("flip-flop {head} must feed exactly one conjunction"))?;
+
+    let mut bits = Vec::new();
+    let mut period = 0;
+    let mut previous = BROADCASTER;
+    let mut current = Some(head);
+
+    while let Some(bit) = current {
+        let index = bits.len();
+        ensure!(index < 63, "counter starting at {head} is too long");
+
+        let destinations = circuit.destinations(bit);
+        let inputs = circuit.inputs(bit);
+
+        let feeds_hub = destinations.contains(&hub);
+        let fed_by_hub = inputs.contains(&hub);
+
+        if let Some(&input) = inputs.iter().find(|&&x| x != previous && x != hub) {
+            bail!("flip-flop {bit} of counter {hub} has unexpected input {input}");
+        }
+
+        if let Some(&destination) = (destinations.iter())
+            .find(|&&x| x != hub && circuit.kind(x) != Some(ModuleKind::FlipFlop))
+        {
+            bail!("flip-flop {bit} of counter {hub} has unexpected output {destination}");
+        }
 
-    let mut presses = 0u64;
-    let mut pulse_counts = [0i64; 2];
-    let mut pulse_count_product = None;
+        match index {
+            0 => ensure!(
+                feeds_hub && fed_by_hub,
+                "lowest bit {bit} of counter {hub} must be connected both ways to the hub"
+            ),
+            _ => ensure!(
+                feeds_hub != fed_by_hub,
+                "bit {bit} of counter {hub} must be either an input or an output of the hub"
+            ),
+        }
+
+        if feeds_hub {
+            period |= 1 << index;
+        }
+
+        bits.push(bit);
+        previous = bit;
+
+        current = (destinations.iter())
+            .copied()
+            .filter(|&x| x != hub)
+            .at_most_one()
+            .map_err(|_| eyre!("flip-flop {bit} of counter {hub} feeds several flip-flops"))?;
+    }
+
+    let last = bits.last().value()?;
+    ensure!(
+        circuit.destinations(last).contains(&hub),
+        "highest bit {last} of counter {hub} must feed the hub"
+    );
 
-    let mut current_pulses = VecDeque::new();
+    let hub_inputs: HashSet<_> = circuit.inputs(hub).iter().copied().collect();
+    let expected_inputs: HashSet<_> = (bits.iter().enumerate())
+        .filter(|&(index, _)| period & (1 << index) != 0)
+        .map(|(_, &bit)| bit)
+        .collect();
+    ensure!(
+        hub_inputs == expected_inputs,
+        "hub {hub} has inputs outside of its counter"
+    );
 
-    let initial_pulses = (graph["broadcaster"].destinations.iter())
-        .map(|&destination| ("broadcaster", Pulse::Low, destination))
+    let outputs = (circuit.destinations(hub).iter())
+        .filter(|&x| !bits.contains(x))
         .collect_vec();
+    let &[&output] = &outputs[..] else {
+        bail!("hub {hub} must have exactly one output outside of its counter");
+    };
 
-    let (result1, result2) = 'outer: loop {
-        presses += 1;
+    let mut inverters = Vec::new();
+    let mut current = output;
+
+    while current != collector {
+        ensure!(
+            circuit.kind(current) == Some(ModuleKind::Conjunction)
+                && circuit.inputs(current).len() == 1,
+            "module {current} after counter {hub} is not an inverter"
+        );
+        ensure!(
+            !inverters.contains(&current),
+            "inverters after counter {hub} form a loop"
+        );
+
+        inverters.push(current);
+
+        let destinations = circuit.destinations(current);
+        ensure!(
+            destinations.len() == 1,
+            "inverter {current} after counter {hub} must have a single output"
+        );
+        current = destinations[0];
+    }
 
-        current_pulses.clear();
-        current_pulses.extend(initial_pulses.iter().copied());
+    ensure!(
+        inverters.len() % 2 == 1,
+        "counter {hub} must reach the collector through an odd number of inverters"
+    );
 
-        pulse_counts[0] += 1;
+    Ok(Counter {
+        bits,
+        hub,
+        inverters,
+        period,
+    })
+}
 
-        while let Some((input_name, pulse, output_name)) = current_pulses.pop_front() {
-            pulse_counts[pulse as usize] += 1;
+/// Check that the sink is fed by a conjunction of independent counters
+fn analyze<'a>(circuit: &Circuit<'a>) -> Result<Analysis<'a>> {
+    let collector = (circuit.inputs(SINK).iter())
+        .copied()
+        .exactly_one()
+        .map_err(|_| eyre!("{SINK} must have exactly one input"))?;
 
-            let Some(module) = graph.get_mut(output_name) else {
-                continue;
-            };
+    ensure!(
+        circuit.kind(collector) == Some(ModuleKind::Conjunction),
+        "{SINK} input {collector} is not a conjunction"
+    );
+    ensure!(
+        circuit.destinations(collector) == [SINK],
+        "collector {collector} must only feed {SINK}"
+    );
 
-            if matches!(pulse, Pulse::High)
-                && let Some(cycle) = cycles.get_mut(input_name)
-            {
-                match cycle.len() {
-                    0 => cycle.push(presses),
-                    1 => {
-                        ensure!(presses - cycle[0] == cycle[0]);
-                        cycle.push(presses);
-                    }
-                    _ => (),
-                }
-            }
+    let counters: Vec<_> = (circuit.destinations(BROADCASTER).iter())
+        .map(|&head| analyze_counter(circuit, head, collector))
+        .try_collect()?;
+
+    let mut seen = HashSet::new();
+    for counter in &counters {
+        for module in counter.modules() {
+            ensure!(
+                seen.insert(module),
+                "module {module} is shared by several counters"
+            );
+        }
+    }
 
-            if cycles.values().all(|cycle| cycle.len() == 2) {
-                let result1 = pulse_count_product.value()?;
-                let result2 = cycles.values().map(|x| x[0]).fold(1, lcm);
-                break 'outer (result1, result2);
-            }
+    let collector_inputs: HashSet<_> = circuit.inputs(collector).iter().copied().collect();
+    let counter_outputs: HashSet<_> = (counters.iter())
+        .filter_map(|counter| counter.inverters.last().copied())
+        .collect();
+    ensure!(
+        collector_inputs == counter_outputs,
+        "collector {collector} must be fed by exactly one inverter of each counter"
+    );
 
-            match &mut module.state {
-                ModuleState::None => (),
-                ModuleState::FlipFlop(state) => {
-                    if matches!(pulse, Pulse::Low) {
-                        let new_pulse = if *state { Pulse::Low } else { Pulse::High };
+    let unused = (circuit.modules.keys())
+        .filter(|&&name| name != BROADCASTER && name != collector && !seen.contains(name))
+        .sorted_unstable()
+        .collect_vec();
+    ensure!(
+        unused.is_empty(),
+        "modules outside of the counters: {}",
+        unused.iter().join(", ")
+    );
 
-                        current_pulses.extend(
-                            (module.destinations.iter()).map(|&x| (output_name, new_pulse, x)),
-                        );
+    Ok(Analysis {
+        collector,
+        counters,
+    })
+}
 
-                        *state = !*state;
-                    }
-                }
-                ModuleState::Conjunction(memory) => {
-                    *memory.get_mut(input_name).value()? = pulse;
+/// Simulate until each hub has reset its counter, and compare with the derived periods
+fn check_periods(circuit: &Circuit, analysis: &Analysis) -> Result<()> {
+    let max_period = (analysis.counters.iter())
+        .map(|counter| counter.period)
+        .max()
+        .unwrap_or(0);
 
-                    let new_pulse = if memory.values().all(|pulse| matches!(pulse, Pulse::High)) {
-                        Pulse::Low
-                    } else {
-                        Pulse::High
-                    };
+    let mut first_resets = HashMap::new();
+    let mut simulator = Simulator::new(circuit);
 
-                    current_pulses
-                        .extend((module.destinations.iter()).map(|&x| (output_name, new_pulse, x)));
-                }
+    for presses in 1..=max_period {
+        simulator.press(|source, pulse, _| {
+            if pulse == Pulse::Low {
+                first_resets.entry(source).or_insert(presses);
             }
-        }
+        });
+    }
 
-        if presses == 1000 {
-            pulse_count_product = Some(pulse_counts.iter().product::<i64>());
-        }
-    };
+    for counter in &analysis.counters {
+        let first_reset = first_resets.get(counter.hub).copied();
+        ensure!(
+            first_reset == Some(counter.period),
+            "counter {} resets at press {first_reset:?} instead of {}",
+            counter.hub,
+            counter.period
+        );
+    }
+
+    Ok(())
+}
+
+fn gcd(mut x: u64, mut y: u64) -> u64 {
+    while y != 0 {
+        (x, y) = (y, x % y);
+    }
+    x
+}
+
+fn lcm(x: u64, y: u64) -> u64 {
+    x * y / gcd(x, y)
+}
 
+fn main() -> Result<()> {
+    let input = setup(file!())?;
+    let input = String::from_utf8_lossy(&input);
+
+    let circuit = Circuit::parse(&input)?;
+
+    if flag("--dot") {
+        print!("{}", circuit.to_dot());
+        return Ok(());
+    }
+
+    let mut pulse_counts = [0u64; 2];
+    let mut simulator = Simulator::new(&circuit);
+
+    for _ in 0..PRESSES {
+        simulator.press(|_, pulse, _| pulse_counts[pulse as usize] += 1);
+    }
+
+    let result1 = pulse_counts.iter().product::<u64>();
     println!("{result1}");
+
+    let analysis = analyze(&circuit).wrap_err("unsupported circuit")?;
+
+    if flag("--analysis") {
+        println!("collector: {}", analysis.collector);
+        for counter in &analysis.counters {
+            println!(
+                "counter {}: {} bits, period {}",
+                counter.hub,
+                counter.bits.len(),
+                counter.period
+            );
+        }
+    }
+
+    if flag("--check") {
+        check_periods(&circuit, &analysis)?;
+    }
+
+    let result2 = (analysis.counters.iter())
+        .map(|counter| counter.period)
+        .fold(1, lcm);
+
     println!("{result2}");
     Ok(())
 }
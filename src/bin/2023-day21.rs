@@ -1,141 +1,344 @@
 use aoc::*;
 
-use eyre::ensure;
+use eyre::{bail, ensure, eyre};
 use itertools::Itertools;
-use num_complex::Complex;
-
-use std::collections::VecDeque;
-use std::collections::hash_map::{Entry, HashMap};
-
-const NORTH: Complex<i64> = Complex::new(0, 1);
-const SOUTH: Complex<i64> = Complex::new(0, -1);
-const WEST: Complex<i64> = Complex::new(-1, 0);
-const EAST: Complex<i64> = Complex::new(1, 0);
-const DIRECTIONS: [Complex<i64>; 4] = [NORTH, SOUTH, WEST, EAST];
-
-#[derive(Copy, Clone)]
-enum Tile {
-    Start,
-    Empty,
-    Rock,
+
+const STEPS_1: u64 = 64;
+const STEPS_2: u64 = 26501365;
+
+/// Numbers of map widths added to the part 2 remainder for the cross-check of the closed form
+const CHECK_WIDTHS: [u64; 3] = [2, 3, 4];
+
+/// Numbers of periods past the last sample for the cross-check of the extrapolation
+const CHECK_PERIODS: [u64; 2] = [1, 2];
+
+/// Number of periods skipped before sampling the quadratic sequence
+const WARMUP_PERIODS: u64 = 2;
+
+/// Largest side of a distance window, to fail instead of exhausting memory
+const MAX_WINDOW_SIDE: usize = 8192;
+
+const UNREACHED: u32 = u32::MAX;
+
+fn gcd(mut x: usize, mut y: usize) -> usize {
+    while y != 0 {
+        (x, y) = (y, x % y);
+    }
+    x
+}
+
+fn lcm(x: usize, y: usize) -> Option<usize> {
+    (x / gcd(x, y)).checked_mul(y)
+}
+
+/// Distances from the start on a square window of the infinite map, centered on the start
+struct Distances {
+    values: Vec<u32>,
+}
+
+impl Distances {
+    /// Count the positions which can be reached in exactly `steps` steps
+    fn count(&self, steps: u64) -> u64 {
+        (self.values.iter())
+            .filter(|&&d| {
+                d != UNREACHED && d as u64 <= steps && (steps - d as u64).is_multiple_of(2)
+            })
+            .count() as u64
+    }
 }
 
-struct Grid {
-    size: usize,
-    tiles: Vec<Tile>,
+struct Garden {
+    width: usize,
+    height: usize,
+    rocks: Vec<bool>,
+    start: (usize, usize),
 }
 
-impl Grid {
-    fn new(size: usize, tiles: Vec<Tile>) -> Result<Self> {
+impl Garden {
+    fn parse(input: &str) -> Result<Self> {
+        let lines = input.lines().collect_vec();
+
+        let width = lines.first().value()?.len();
+        let height = lines.len();
         ensure!(
-            size * size == tiles.len(),
-            "unable to construct Grid: size * size != tiles.len()"
+            lines.iter().all(|line| line.len() == width),
+            "map should be rectangular"
         );
 
-        Ok(Self { size, tiles })
+        let mut start = None;
+        let mut rocks = Vec::with_capacity(width * height);
+
+        for (y, line) in lines.iter().enumerate() {
+            for (x, tile) in line.bytes().enumerate() {
+                match tile {
+                    b'S' => {
+                        ensure!(start.is_none(), "multiple start positions");
+                        start = Some((x, y));
+                        rocks.push(false);
+                    }
+                    b'.' => rocks.push(false),
+                    b'#' => rocks.push(true),
+                    _ => bail!("unknown tile: {}", tile as char),
+                }
+            }
+        }
+
+        Ok(Self {
+            width,
+            height,
+            rocks,
+            start: start.value()?,
+        })
     }
 
-    fn get_index(&self, position: &Complex<i64>) -> usize {
-        (self.size - 1 - position.im as usize) * self.size + position.re as usize
+    fn is_rock(&self, x: usize, y: usize) -> bool {
+        self.rocks[y * self.width + x]
     }
 
-    fn get_position(&self, index: usize) -> Complex<i64> {
-        let row = index / self.size;
-        let column = index % self.size;
-        Complex::new(column as i64, (self.size - 1 - row) as i64)
+    /// Compute the distances from the start on the infinite map, up to `radius` steps
+    fn distances(&self, radius: usize) -> Result<Distances> {
+        ensure!(
+            radius < MAX_WINDOW_SIDE / 2,
+            "distance window of {radius} steps is too large"
+        );
+
+        let side = 2 * radius + 1;
+        let mut values = vec![UNREACHED; side * side];
+
+        // Offsets so that window coordinates can be converted to map coordinates without underflow
+        let offset_x = radius.next_multiple_of(self.width) + self.start.0 - radius;
+        let offset_y = radius.next_multiple_of(self.height) + self.start.1 - radius;
+
+        let mut current = vec![(radius, radius)];
+        let mut next = Vec::new();
+        values[radius * side + radius] = 0;
+
+        for distance in 1..=radius as u32 {
+            for &(x, y) in &current {
+                let neighbors = [
+                    (x > 0).then(|| (x - 1, y)),
+                    (x + 1 < side).then_some((x + 1, y)),
+                    (y > 0).then(|| (x, y - 1)),
+                    (y + 1 < side).then_some((x, y + 1)),
+                ];
+
+                for (x, y) in neighbors.into_iter().flatten() {
+                    let index = y * side + x;
+                    let map_x = (x + offset_x) % self.width;
+                    let map_y = (y + offset_y) % self.height;
+
+                    if values[index] == UNREACHED && !self.is_rock(map_x, map_y) {
+                        values[index] = distance;
+                        next.push((x, y));
+                    }
+                }
+            }
+
+            std::mem::swap(&mut current, &mut next);
+            next.clear();
+        }
+
+        Ok(Distances { values })
     }
-}
 
-fn main() -> Result<()> {
-    let input = setup(file!())?;
-    let input = String::from_utf8_lossy(&input);
+    /// Compute the distances from the start, without leaving the original map
+    fn tile_distances(&self) -> Vec<u32> {
+        let mut values = vec![UNREACHED; self.rocks.len()];
+        let mut current = vec![self.start];
+        let mut next = Vec::new();
+        values[self.start.1 * self.width + self.start.0] = 0;
 
-    let width = input.lines().next().value()?.len();
-    let height = input.lines().count();
+        let mut distance = 0;
+        while !current.is_empty() {
+            distance += 1;
 
-    ensure!(width == height, "unsupported input");
+            for &(x, y) in &current {
+                let neighbors = [
+                    (x > 0).then(|| (x - 1, y)),
+                    (x + 1 < self.width).then_some((x + 1, y)),
+                    (y > 0).then(|| (x, y - 1)),
+                    (y + 1 < self.height).then_some((x, y + 1)),
+                ];
 
-    let size = width;
+                for (x, y) in neighbors.into_iter().flatten() {
+                    let index = y * self.width + x;
+                    if values[index] == UNREACHED && !self.rocks[index] {
+                        values[index] = distance;
+                        next.push((x, y));
+                    }
+                }
+            }
 
-    let tiles = input
-        .bytes()
-        .filter_map(|x| match x {
-            b'S' => Some(Tile::Start),
-            b'.' => Some(Tile::Empty),
-            b'#' => Some(Tile::Rock),
-            _ => None,
-        })
-        .collect_vec();
-
-    let grid = Grid::new(size, tiles)?;
-
-    let start_position = grid
-        .tiles
-        .iter()
-        .position(|tile| matches!(tile, Tile::Start))
-        .map(|index| grid.get_position(index))
-        .value()?;
-
-    let mut current_states = VecDeque::from([(start_position, 0u64)]);
-    let mut visited = HashMap::from([(start_position, 0)]);
-
-    while let Some((position, steps)) = current_states.pop_front() {
-        current_states.extend(
-            DIRECTIONS
-                .into_iter()
-                .map(|direction| (position + direction, steps + 1))
-                .filter(|&(new_position, _)| {
-                    (0..grid.size as i64).contains(&new_position.re)
-                        && (0..grid.size as i64).contains(&new_position.im)
-                        && !matches!(grid.tiles[grid.get_index(&new_position)], Tile::Rock)
-                })
-                .filter(
-                    |&(new_position, new_steps)| match visited.entry(new_position) {
-                        Entry::Vacant(entry) => {
-                            entry.insert(new_steps);
-                            true
-                        }
-                        Entry::Occupied(mut entry) => {
-                            if new_steps < *entry.get() {
-                                entry.insert(new_steps);
-                                true
-                            } else {
-                                false
-                            }
-                        }
-                    },
-                ),
-        );
+            std::mem::swap(&mut current, &mut next);
+            next.clear();
+        }
+
+        values
     }
 
-    let result1 = visited
-        .values()
-        .filter(|&&steps| steps <= 64 && steps.is_multiple_of(2))
-        .count();
+    /// Check the properties of the map required by the closed form for a step count
+    fn supports_closed_form(&self, steps: u64) -> bool {
+        let size = self.width;
+        let half = size / 2;
 
-    let n = (26501365 - grid.size / 2) / grid.size;
+        let is_clear_row = |y| (0..size).all(|x| !self.is_rock(x, y));
+        let is_clear_column = |x| (0..size).all(|y| !self.is_rock(x, y));
 
-    let mut odd_tiles = 0;
-    let mut even_tiles = 0;
-    let mut odd_corners = 0;
-    let mut even_corners = 0;
+        self.width == self.height
+            && size % 2 == 1
+            && self.start == (half, half)
+            && is_clear_row(half)
+            && is_clear_column(half)
+            && is_clear_row(0)
+            && is_clear_row(size - 1)
+            && is_clear_column(0)
+            && is_clear_column(size - 1)
+            && steps >= half as u64
+            && (steps - half as u64).is_multiple_of(size as u64)
+    }
 
-    for &steps in visited.values() {
-        if steps.is_multiple_of(2) {
-            even_tiles += 1;
-            if steps as usize > grid.size / 2 {
-                even_corners += 1;
+    /// Count the reachable positions by counting the number of full and partial copies of the map
+    fn closed_form(&self, steps: u64) -> Option<u64> {
+        if !self.supports_closed_form(steps) {
+            return None;
+        }
+
+        let half = (self.width / 2) as u64;
+        let n = (steps - half) / self.width as u64;
+
+        let (mut same_parity, mut other_parity) = ([0u64; 2], [0u64; 2]);
+
+        for distance in self.tile_distances() {
+            if distance == UNREACHED {
+                continue;
             }
-        } else {
-            odd_tiles += 1;
-            if steps as usize > grid.size / 2 {
-                odd_corners += 1;
+
+            let counts = match (steps - distance as u64).is_multiple_of(2) {
+                true => &mut same_parity,
+                false => &mut other_parity,
+            };
+            counts[0] += 1;
+            if distance as u64 > half {
+                counts[1] += 1;
             }
         }
+
+        // Copies at an even distance from the center have the same parity as the center copy
+        let ([tiles, corners], [other_tiles, other_corners]) = match n.is_multiple_of(2) {
+            true => (same_parity, other_parity),
+            false => (other_parity, same_parity),
+        };
+
+        Some(
+            (n + 1).pow(2) * tiles + n.pow(2) * other_tiles - (n + 1) * corners + n * other_corners,
+        )
+    }
+
+    /// Period of the growth of the count, after which the frontier reaches the same position in
+    /// the map copies with the same parity, and the step counts sampled for a given step count
+    fn samples(&self, steps: u64) -> Result<(u64, [u64; 4])> {
+        let period = lcm(self.width, self.height)
+            .and_then(|x| x.checked_mul(2))
+            .ok_or_else(|| eyre!("map period overflows"))? as u64;
+        let remainder = steps % period;
+
+        // Saturated step counts are too large for a distance window and fail when sampled
+        let samples = [0, 1, 2, 3]
+            .map(|k| ((WARMUP_PERIODS + k).saturating_mul(period)).saturating_add(remainder));
+
+        Ok((period, samples))
+    }
+
+    /// Count the reachable positions by extrapolating the quadratic growth of the count,
+    /// sampled at step counts with the same remainder modulo the map period.
+    fn extrapolate(&self, steps: u64) -> Result<u64> {
+        let (period, samples) = self.samples(steps)?;
+        let max_sample = samples[3];
+
+        if steps <= max_sample {
+            return self.count_bfs(steps);
+        }
+
+        let distances = self.distances(max_sample.try_into()?)?;
+        let [f0, f1, f2, f3] = samples.map(|x| distances.count(x) as i128);
+
+        ensure!(
+            f3 - 3 * f2 + 3 * f1 - f0 == 0,
+            "reachable counts do not grow quadratically"
+        );
+
+        let n = ((steps - samples[0]) / period) as i128;
+        let (d1, d2) = (f1 - f0, f2 - 2 * f1 + f0);
+
+        Ok((f0 + n * d1 + n * (n - 1) / 2 * d2).try_into()?)
+    }
+
+    /// Count the positions which can be reached in exactly `steps` steps on the infinite map
+    fn count(&self, steps: u64) -> Result<u64> {
+        match self.closed_form(steps) {
+            Some(count) => Ok(count),
+            None => self.extrapolate(steps),
+        }
+    }
+
+    fn count_bfs(&self, steps: u64) -> Result<u64> {
+        Ok(self.distances(steps.try_into()?)?.count(steps))
+    }
+}
+
+fn main() -> Result<()> {
+    let input = setup(file!())?;
+    let input = String::from_utf8_lossy(&input);
+
+    let garden = Garden::parse(&input)?;
+
+    if flag("--check") {
+        // Step counts with the same remainder as part 2 modulo the map width, where the closed
+        // form applies on inputs with clear lanes around the start
+        let width = garden.width as u64;
+        let closed_form_steps = CHECK_WIDTHS.map(|k| STEPS_2 % width + k * width);
+
+        // Step counts past the last sample, where the extrapolation is actually used
+        let (period, samples) = garden.samples(STEPS_2)?;
+        let extrapolated_steps = CHECK_PERIODS.map(|k| samples[3].saturating_add(k * period));
+
+        for steps in closed_form_steps {
+            let count_bfs = garden.count_bfs(steps)?;
+            if let Some(count) = garden.closed_form(steps) {
+                ensure!(
+                    count == count_bfs,
+                    "closed form mismatch for {steps} steps: {count} != {count_bfs}"
+                );
+            }
+            println!("{steps} steps: {count_bfs}");
+        }
+
+        for steps in extrapolated_steps {
+            let count = garden.extrapolate(steps)?;
+            let count_bfs = garden.count_bfs(steps)?;
+            ensure!(
+                count == count_bfs,
+                "extrapolation mismatch for {steps} steps: {count} != {count_bfs}"
+            );
+            println!("{steps} steps: {count}");
+        }
+
+        if let Some(count) = garden.closed_form(STEPS_2) {
+            let extrapolated = garden.extrapolate(STEPS_2)?;
+            ensure!(
+                count == extrapolated,
+                "mismatch between closed form and extrapolation"
+            );
+        }
+        println!();
+    }
+
+    if let Some(steps) = option("--steps")? {
+        println!("{steps} steps: {}\n", garden.count(steps)?);
     }
 
-    let result2 = (n + 1).pow(2) * odd_tiles + n.pow(2) * even_tiles - (n + 1) * odd_corners
-        + n * even_corners;
+    let result1 = garden.count(STEPS_1)?;
+    let result2 = garden.count(STEPS_2)?;
 
     println!("{result1}");
     println!("{result2}");
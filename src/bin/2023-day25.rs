@@ -1,101 +1,83 @@
+use aoc::dot::Dot;
+use aoc::graph::Graph;
 use aoc::*;
 
+use eyre::ensure;
 use itertools::Itertools;
 
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::HashMap;
 
-fn compute_farthest_node<'a>(
-    graph: &HashMap<&str, Vec<&'a str>>,
-    node: &'a str,
-    current_states: &mut Vec<(&'a str, u64)>,
-    visited: &mut HashSet<&'a str>,
-) -> &'a str {
-    visited.clear();
+const CUT_SIZE: u64 = 3;
 
-    current_states.clear();
-    current_states.push((node, 0));
+fn main() -> Result<()> {
+    let input = setup(file!())?;
+    let input = String::from_utf8_lossy(&input);
 
-    let mut max_steps = 0u64;
-    let mut farthest_node = node;
+    let mut graph = Graph::default();
+    let mut indices = HashMap::new();
+    let mut names = Vec::new();
 
-    while let Some((current_node, steps)) = current_states.pop() {
-        if steps > max_steps {
-            max_steps = steps;
-            farthest_node = current_node;
+    for line in input.lines() {
+        let (node, links) = line.split(": ").next_tuple().value()?;
+        let node = *indices.entry(node).or_insert_with(|| {
+            names.push(node);
+            graph.add_node()
+        });
+
+        for link in links.split_ascii_whitespace() {
+            let link = *indices.entry(link).or_insert_with(|| {
+                names.push(link);
+                graph.add_node()
+            });
+            graph.add_edge(node, link, 1);
         }
-
-        current_states.extend(
-            graph[current_node]
-                .iter()
-                .filter(|&&link| visited.insert(link))
-                .map(|&link| (link, steps + 1)),
-        );
     }
 
-    farthest_node
-}
+    let cut = graph.min_cut_by_max_flow().value()?;
 
-fn compute_group_size<'a>(
-    graph: &HashMap<&str, Vec<&'a str>>,
-    start_node: &'a str,
-    end_node: &str,
-    visited: &mut HashSet<&'a str>,
-) -> usize {
-    let mut visited_edges = HashSet::new();
-    let mut current_states = VecDeque::new();
-
-    for _ in 0..4 {
-        visited.clear();
-
-        current_states.clear();
-        current_states.push_back((start_node, Vec::new()));
-
-        while let Some((node, path)) = current_states.pop_front() {
-            if !visited.insert(node) {
-                continue;
-            }
-
-            if node == end_node {
-                visited_edges.extend(path.windows(2).flat_map(|x| [(x[0], x[1]), (x[1], x[0])]));
-                break;
-            }
-
-            for &link in &graph[node] {
-                if !visited_edges.contains(&(node, link)) {
-                    let new_path = path.iter().copied().chain([link]).collect_vec();
-                    current_states.push_back((link, new_path));
-                }
-            }
-        }
+    if flag("--check") {
+        let other = graph.stoer_wagner().value()?;
+        ensure!(
+            other.weight == cut.weight,
+            "Stoer–Wagner found a cut of weight {}, max-flow found {}",
+            other.weight,
+            cut.weight
+        );
     }
 
-    visited.len()
-}
-
-fn main() -> Result<()> {
-    let input = setup(file!())?;
-    let input = String::from_utf8_lossy(&input);
+    if flag("--dot") {
+        let mut dot = Dot::graph();
 
-    let mut graph = HashMap::<_, Vec<_>>::new();
+        for (index, name) in names.iter().enumerate() {
+            let color = if cut.side[index] {
+                "lightblue"
+            } else {
+                "lightpink"
+            };
+            dot.node(name, &[("style", "filled"), ("fillcolor", color)]);
+        }
 
-    for line in input.lines() {
-        let (node, links) = line.split(": ").next_tuple().value()?;
-        let links = links.split_ascii_whitespace().collect_vec();
-        for link in links {
-            graph.entry(node).or_default().push(link);
-            graph.entry(link).or_default().push(node);
+        for (u, v, _) in graph.edges() {
+            let attributes: &[_] = match cut.side[u] != cut.side[v] {
+                true => &[("color", "red"), ("penwidth", "3")],
+                false => &[],
+            };
+            dot.edge(names[u], names[v], attributes);
         }
-    }
 
-    let mut current_states = Vec::new();
-    let mut visited = HashSet::new();
+        print!("{dot}");
+        return Ok(());
+    }
 
-    let first_node = input.split(':').next().value()?;
-    let start_node = compute_farthest_node(&graph, first_node, &mut current_states, &mut visited);
-    let end_node = compute_farthest_node(&graph, start_node, &mut current_states, &mut visited);
+    ensure!(
+        cut.weight == CUT_SIZE && cut.edges.len() == CUT_SIZE as usize,
+        "minimum cut should have {CUT_SIZE} edges, found {} edges of total weight {}",
+        cut.edges.len(),
+        cut.weight
+    );
 
-    let group_size = compute_group_size(&graph, start_node, end_node, &mut visited);
-    let result = group_size * (graph.len() - group_size);
+    let (group_size_1, group_size_2) = cut.partition_sizes();
+    let result = group_size_1 * group_size_2;
 
     println!("{result}");
     Ok(())
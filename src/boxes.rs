@@ -0,0 +1,273 @@
+use itertools::Itertools;
+
+use std::array;
+
+/// Axis-aligned box of integer points, from `start` included to `end` excluded on each axis
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Box<const D: usize> {
+    pub start: [i64; D],
+    pub end: [i64; D],
+}
+
+impl<const D: usize> Box<D> {
+    pub fn new(start: [i64; D], end: [i64; D]) -> Self {
+        Self { start, end }
+    }
+
+    /// Box from `start` to `end`, both included
+    pub fn from_inclusive(start: [i64; D], end: [i64; D]) -> Self {
+        Self::new(start, end.map(|x| x + 1))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        (0..D).any(|axis| self.start[axis] >= self.end[axis])
+    }
+
+    pub fn volume(&self) -> i64 {
+        (0..D)
+            .map(|axis| (self.end[axis] - self.start[axis]).max(0))
+            .product()
+    }
+
+    pub fn contains(&self, point: [i64; D]) -> bool {
+        (0..D).all(|axis| (self.start[axis]..self.end[axis]).contains(&point[axis]))
+    }
+
+    pub fn contains_box(&self, other: &Self) -> bool {
+        (0..D)
+            .all(|axis| self.start[axis] <= other.start[axis] && other.end[axis] <= self.end[axis])
+    }
+
+    pub fn intersect(&self, other: &Self) -> Option<Self> {
+        let start = array::from_fn(|axis| self.start[axis].max(other.start[axis]));
+        let end = array::from_fn(|axis| self.end[axis].min(other.end[axis]));
+        let intersection = Self::new(start, end);
+        (!intersection.is_empty()).then_some(intersection)
+    }
+
+    /// Split the points of `self` outside of `other` into at most `2 * D` disjoint boxes
+    pub fn subtract(&self, other: &Self) -> Vec<Self> {
+        let Some(intersection) = self.intersect(other) else {
+            return vec![*self];
+        };
+
+        let mut pieces = Vec::new();
+        let mut remaining = *self;
+
+        for axis in 0..D {
+            if remaining.start[axis] < intersection.start[axis] {
+                let mut piece = remaining;
+                piece.end[axis] = intersection.start[axis];
+                pieces.push(piece);
+                remaining.start[axis] = intersection.start[axis];
+            }
+
+            if intersection.end[axis] < remaining.end[axis] {
+                let mut piece = remaining;
+                piece.start[axis] = intersection.end[axis];
+                pieces.push(piece);
+                remaining.end[axis] = intersection.end[axis];
+            }
+        }
+
+        pieces
+    }
+
+    /// Split the box in `2^D` boxes by halving each axis, skipping empty boxes
+    pub fn split(&self) -> Vec<Self> {
+        (0..1 << D)
+            .map(|corner: usize| {
+                let mut piece = *self;
+                for axis in 0..D {
+                    let middle = self.start[axis] + (self.end[axis] - self.start[axis]) / 2;
+                    if corner & (1 << axis) == 0 {
+                        piece.end[axis] = middle;
+                    } else {
+                        piece.start[axis] = middle;
+                    }
+                }
+                piece
+            })
+            .filter(|piece| !piece.is_empty())
+            .collect()
+    }
+
+    /// Manhattan distance from a point to the nearest point of the box
+    pub fn distance_to(&self, point: [i64; D]) -> i64 {
+        (0..D)
+            .map(|axis| {
+                (self.start[axis] - point[axis]).max(0)
+                    + (point[axis] - (self.end[axis] - 1)).max(0)
+            })
+            .sum()
+    }
+}
+
+/// Set of integer points, stored as a union of disjoint boxes
+#[derive(Clone, Debug)]
+pub struct BoxSet<const D: usize> {
+    boxes: Vec<Box<D>>,
+}
+
+impl<const D: usize> Default for BoxSet<D> {
+    fn default() -> Self {
+        Self { boxes: Vec::new() }
+    }
+}
+
+impl<const D: usize> BoxSet<D> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn boxes(&self) -> &[Box<D>] {
+        &self.boxes
+    }
+
+    pub fn volume(&self) -> i64 {
+        self.boxes.iter().map(Box::volume).sum()
+    }
+
+    pub fn insert(&mut self, new_box: Box<D>) {
+        self.remove(&new_box);
+        if !new_box.is_empty() {
+            self.boxes.push(new_box);
+        }
+    }
+
+    pub fn remove(&mut self, removed_box: &Box<D>) {
+        let mut pieces = Vec::new();
+
+        self.boxes.retain(|b| {
+            if b.intersect(removed_box).is_none() {
+                return true;
+            }
+            pieces.extend(b.subtract(removed_box));
+            false
+        });
+
+        self.boxes.extend(pieces);
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        let mut set = self.clone();
+        for &b in &other.boxes {
+            set.insert(b);
+        }
+        set
+    }
+
+    pub fn subtract(&self, other: &Self) -> Self {
+        let mut set = self.clone();
+        for b in &other.boxes {
+            set.remove(b);
+        }
+        set
+    }
+}
+
+/// Box added to (`true`) or removed from (`false`) a set of points
+pub type Operation<const D: usize> = (bool, Box<D>);
+
+/// Algorithm computing the volume of a sequence of operations
+#[derive(Copy, Clone, Debug)]
+pub enum Backend {
+    /// Maintain a [`BoxSet`] of disjoint boxes
+    Subtraction,
+    /// Compress the coordinates on each axis, and find the last operation covering each cell
+    Compression,
+    /// Sweep a hyperplane along the first axis, computing the volume of each slice by compression
+    Sweep,
+}
+
+impl Backend {
+    pub const ALL: [Self; 3] = [Self::Subtraction, Self::Compression, Self::Sweep];
+}
+
+/// Compute the number of points in the set obtained by applying a sequence of operations to an empty set
+pub fn volume<const D: usize>(operations: &[Operation<D>], backend: Backend) -> i64 {
+    match backend {
+        Backend::Subtraction => {
+            let mut set = BoxSet::new();
+            for (add, b) in operations {
+                match add {
+                    true => set.insert(*b),
+                    false => set.remove(b),
+                }
+            }
+            set.volume()
+        }
+        Backend::Compression => {
+            let operations = operations
+                .iter()
+                .filter(|(_, b)| !b.is_empty())
+                .collect_vec();
+            compressed_volume(&operations, 0)
+        }
+        Backend::Sweep => sweep_volume(operations),
+    }
+}
+
+/// Compute the volume on the axes from `axis`, where the last operation covering a cell decides if it is in the set
+fn compressed_volume<const D: usize>(operations: &[&Operation<D>], axis: usize) -> i64 {
+    if axis == D {
+        return operations.last().map_or(0, |&&(add, _)| add as i64);
+    }
+
+    let coordinates = (operations.iter())
+        .flat_map(|(_, b)| [b.start[axis], b.end[axis]])
+        .sorted_unstable()
+        .dedup()
+        .collect_vec();
+
+    let mut slice = Vec::with_capacity(operations.len());
+
+    coordinates
+        .windows(2)
+        .map(|window| {
+            let (low, high) = (window[0], window[1]);
+
+            slice.clear();
+            slice.extend(
+                (operations.iter()).filter(|(_, b)| b.start[axis] <= low && high <= b.end[axis]),
+            );
+
+            (high - low) * compressed_volume(&slice, axis + 1)
+        })
+        .sum()
+}
+
+fn sweep_volume<const D: usize>(operations: &[Operation<D>]) -> i64 {
+    let mut events = (operations.iter().enumerate())
+        .filter(|(_, (_, b))| !b.is_empty())
+        .flat_map(|(index, (_, b))| [(b.start[0], true, index), (b.end[0], false, index)])
+        .collect_vec();
+
+    events.sort_unstable_by_key(|&(x, ..)| x);
+
+    let mut active: Vec<usize> = Vec::new();
+    let mut total = 0;
+    let mut previous_x = None;
+
+    for (x, entering, index) in events {
+        if let Some(previous_x) = previous_x
+            && x > previous_x
+            && !active.is_empty()
+        {
+            let slice = active.iter().map(|&index| &operations[index]).collect_vec();
+            total += (x - previous_x) * compressed_volume(&slice, 1);
+        }
+
+        match entering {
+            true => {
+                let position = active.partition_point(|&i| i < index);
+                active.insert(position, index);
+            }
+            false => active.retain(|&i| i != index),
+        }
+
+        previous_x = Some(x);
+    }
+
+    total
+}
@@ -0,0 +1,286 @@
+use crate::*;
+
+use eyre::{bail, eyre};
+use itertools::Itertools;
+use smallvec::SmallVec;
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::Debug;
+use std::hash::Hash;
+
+/// Dependency graph where each node is computed from the values of its dependencies
+pub struct Dag<K, N> {
+    nodes: HashMap<K, (N, SmallVec<[K; 2]>)>,
+    dependents: HashMap<K, Vec<K>>,
+}
+
+impl<K, N> Default for Dag<K, N> {
+    fn default() -> Self {
+        Self {
+            nodes: HashMap::new(),
+            dependents: HashMap::new(),
+        }
+    }
+}
+
+impl<K: Copy + Eq + Hash + Debug, N> Dag<K, N> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Insert a node, replacing any previous node with the same key
+    pub fn insert(&mut self, key: K, node: N, dependencies: impl IntoIterator<Item = K>) {
+        let dependencies: SmallVec<[K; 2]> = dependencies.into_iter().collect();
+
+        if let Some((_, old_dependencies)) = self.nodes.remove(&key) {
+            for dependency in old_dependencies {
+                if let Some(dependents) = self.dependents.get_mut(&dependency) {
+                    dependents.retain(|&x| x != key);
+                }
+            }
+        }
+
+        self.dependents.entry(key).or_default();
+        for &dependency in dependencies.iter().unique() {
+            self.dependents.entry(dependency).or_default().push(key);
+        }
+
+        self.nodes.insert(key, (node, dependencies));
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn contains(&self, key: K) -> bool {
+        self.nodes.contains_key(&key)
+    }
+
+    pub fn node(&self, key: K) -> Option<&N> {
+        self.nodes.get(&key).map(|(node, _)| node)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (K, &N)> {
+        self.nodes.iter().map(|(&key, (node, _))| (key, node))
+    }
+
+    pub fn dependencies(&self, key: K) -> &[K] {
+        self.nodes
+            .get(&key)
+            .map_or(&[], |(_, dependencies)| dependencies)
+    }
+
+    pub fn dependents(&self, key: K) -> &[K] {
+        self.dependents
+            .get(&key)
+            .map_or(&[], |dependents| dependents)
+    }
+
+    /// Compute a topological order of the nodes using Kahn's algorithm
+    pub fn topological_order(&self) -> Result<Vec<K>> {
+        let mut remaining_dependencies = HashMap::with_capacity(self.nodes.len());
+
+        for (&key, (_, dependencies)) in &self.nodes {
+            let mut count = 0;
+            for &dependency in dependencies.iter().unique() {
+                if !self.nodes.contains_key(&dependency) {
+                    bail!("unknown dependency {dependency:?} for node {key:?}");
+                }
+                count += 1;
+            }
+            remaining_dependencies.insert(key, count);
+        }
+
+        let mut queue: VecDeque<_> = remaining_dependencies
+            .iter()
+            .filter(|&(_, &count)| count == 0)
+            .map(|(&key, _)| key)
+            .collect();
+
+        let mut order = Vec::with_capacity(self.nodes.len());
+
+        while let Some(key) = queue.pop_front() {
+            order.push(key);
+
+            for &dependent in self.dependents(key) {
+                let count = remaining_dependencies.get_mut(&dependent).value()?;
+                *count -= 1;
+                if *count == 0 {
+                    queue.push_back(dependent);
+                }
+            }
+        }
+
+        if order.len() != self.nodes.len() {
+            let cycle = self.find_cycle().value()?;
+            bail!("dependency cycle: {}", format_cycle(&cycle));
+        }
+
+        Ok(order)
+    }
+
+    /// Find a dependency cycle, returned as a list of nodes where each node depends on the next one
+    pub fn find_cycle(&self) -> Option<Vec<K>> {
+        let mut done = HashSet::new();
+
+        for &start in self.nodes.keys() {
+            if done.contains(&start) {
+                continue;
+            }
+
+            let mut path = Vec::new();
+            let mut in_path = HashSet::new();
+            let mut stack = vec![(start, false)];
+
+            while let Some((key, expanded)) = stack.pop() {
+                if expanded {
+                    path.pop();
+                    in_path.remove(&key);
+                    done.insert(key);
+                    continue;
+                }
+
+                if done.contains(&key) {
+                    continue;
+                }
+
+                if in_path.contains(&key) {
+                    let position = path.iter().position(|&x| x == key)?;
+                    return Some(path[position..].to_vec());
+                }
+
+                path.push(key);
+                in_path.insert(key);
+                stack.push((key, true));
+                stack.extend(self.dependencies(key).iter().map(|&x| (x, false)));
+            }
+        }
+
+        None
+    }
+}
+
+fn format_cycle<K: Debug>(cycle: &[K]) -> String {
+    cycle
+        .iter()
+        .chain(cycle.first())
+        .map(|key| format!("{key:?}"))
+        .join(" -> ")
+}
+
+/// Memoised evaluation of the nodes of a [`Dag`], with support for overriding node values
+pub struct Evaluator<'a, K, N, V, F> {
+    dag: &'a Dag<K, N>,
+    values: HashMap<K, V>,
+    overrides: HashSet<K>,
+    compute: F,
+}
+
+impl<'a, K, N, V, F> Evaluator<'a, K, N, V, F>
+where
+    K: Copy + Eq + Hash + Debug,
+    F: Fn(&N, &HashMap<K, V>) -> V,
+{
+    /// Create an evaluator, where `compute` is called once all dependencies of a node have a value
+    pub fn new(dag: &'a Dag<K, N>, compute: F) -> Self {
+        Self {
+            dag,
+            values: HashMap::new(),
+            overrides: HashSet::new(),
+            compute,
+        }
+    }
+
+    pub fn values(&self) -> &HashMap<K, V> {
+        &self.values
+    }
+
+    /// Evaluate all nodes in topological order
+    pub fn evaluate_all(&mut self) -> Result<&HashMap<K, V>> {
+        for key in self.dag.topological_order()? {
+            if !self.values.contains_key(&key) {
+                let (node, _) = &self.dag.nodes[&key];
+                let value = (self.compute)(node, &self.values);
+                self.values.insert(key, value);
+            }
+        }
+
+        Ok(&self.values)
+    }
+
+    /// Evaluate a node, computing only the missing values of its transitive dependencies
+    pub fn get(&mut self, key: K) -> Result<&V> {
+        if !self.values.contains_key(&key) {
+            let mut path = Vec::new();
+            let mut in_path = HashSet::new();
+            let mut stack = vec![(key, false)];
+
+            while let Some((name, expanded)) = stack.pop() {
+                if expanded {
+                    path.pop();
+                    in_path.remove(&name);
+                    let (node, _) = &self.dag.nodes[&name];
+                    let value = (self.compute)(node, &self.values);
+                    self.values.insert(name, value);
+                    continue;
+                }
+
+                if self.values.contains_key(&name) {
+                    continue;
+                }
+
+                if in_path.contains(&name) {
+                    let position = path.iter().position(|&x| x == name).value()?;
+                    bail!("dependency cycle: {}", format_cycle(&path[position..]));
+                }
+
+                let dependencies = match self.dag.nodes.get(&name) {
+                    Some((_, dependencies)) => dependencies,
+                    None => bail!("unknown node: {name:?}"),
+                };
+
+                path.push(name);
+                in_path.insert(name);
+                stack.push((name, true));
+                stack.extend(dependencies.iter().map(|&x| (x, false)));
+            }
+        }
+
+        self.values
+            .get(&key)
+            .ok_or_else(|| eyre!("unknown node: {key:?}"))
+    }
+
+    /// Override the value of a node, invalidating the values of its transitive dependents
+    pub fn set(&mut self, key: K, value: V) {
+        self.invalidate_dependents(key);
+        self.overrides.insert(key);
+        self.values.insert(key, value);
+    }
+
+    /// Remove the override of a node, so that it is computed again from its dependencies
+    pub fn unset(&mut self, key: K) {
+        if self.overrides.remove(&key) {
+            self.invalidate_dependents(key);
+            self.values.remove(&key);
+        }
+    }
+
+    fn invalidate_dependents(&mut self, key: K) {
+        let mut visited = HashSet::from([key]);
+        let mut queue = VecDeque::from([key]);
+
+        while let Some(name) = queue.pop_front() {
+            for &dependent in self.dag.dependents(name) {
+                if !self.overrides.contains(&dependent) && visited.insert(dependent) {
+                    self.values.remove(&dependent);
+                    queue.push_back(dependent);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use std::fmt::{self, Display};
+
+/// Graphviz DOT document, built node by node and edge by edge
+pub struct Dot {
+    directed: bool,
+    statements: Vec<String>,
+}
+
+/// Quote an identifier or attribute value, escaping quotes, backslashes and newlines
+fn quote(text: &str) -> String {
+    let escaped = text
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    format!("\"{escaped}\"")
+}
+
+fn format_attributes(attributes: &[(&str, &str)]) -> String {
+    if attributes.is_empty() {
+        return String::new();
+    }
+
+    let attributes = (attributes.iter())
+        .map(|(key, value)| format!("{key}={}", quote(value)))
+        .collect::<Vec<_>>();
+
+    format!(" [{}]", attributes.join(", "))
+}
+
+impl Dot {
+    /// Directed graph, with edges written `a -> b`
+    pub fn digraph() -> Self {
+        Self {
+            directed: true,
+            statements: Vec::new(),
+        }
+    }
+
+    /// Undirected graph, with edges written `a -- b`
+    pub fn graph() -> Self {
+        Self {
+            directed: false,
+            statements: Vec::new(),
+        }
+    }
+
+    pub fn node(&mut self, id: &str, attributes: &[(&str, &str)]) -> &mut Self {
+        (self.statements).push(format!("{}{}", quote(id), format_attributes(attributes)));
+        self
+    }
+
+    pub fn edge(&mut self, from: &str, to: &str, attributes: &[(&str, &str)]) -> &mut Self {
+        let operator = if self.directed { "->" } else { "--" };
+
+        self.statements.push(format!(
+            "{} {operator} {}{}",
+            quote(from),
+            quote(to),
+            format_attributes(attributes)
+        ));
+        self
+    }
+}
+
+impl Display for Dot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} {{", if self.directed { "digraph" } else { "graph" })?;
+        for statement in &self.statements {
+            writeln!(f, "    {statement};")?;
+        }
+        writeln!(f, "}}")
+    }
+}
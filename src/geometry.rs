@@ -0,0 +1,258 @@
+use crate::rational::{self, Rational};
+use crate::*;
+
+use eyre::{bail, ensure, eyre};
+use itertools::Itertools;
+
+use std::iter;
+
+pub type Point<const D: usize> = [Rational; D];
+
+/// Build a point coordinate by coordinate, failing if any coordinate fails
+fn try_point<const D: usize>(
+    mut coordinate: impl FnMut(usize) -> Result<Rational>,
+) -> Result<Point<D>> {
+    let mut point = [Rational::ZERO; D];
+    for (i, x) in point.iter_mut().enumerate() {
+        *x = coordinate(i)?;
+    }
+    Ok(point)
+}
+
+fn sub<const D: usize>(lhs: &Point<D>, rhs: &Point<D>) -> Result<Point<D>> {
+    try_point(|i| lhs[i].checked_sub(rhs[i]))
+}
+
+fn scale<const D: usize>(v: &Point<D>, factor: Rational) -> Result<Point<D>> {
+    try_point(|i| v[i].checked_mul(factor))
+}
+
+fn dot<const D: usize>(lhs: &Point<D>, rhs: &Point<D>) -> Result<Rational> {
+    (0..D).try_fold(Rational::ZERO, |acc, i| {
+        acc.checked_add(lhs[i].checked_mul(rhs[i])?)
+    })
+}
+
+fn is_zero<const D: usize>(v: &Point<D>) -> bool {
+    v.iter().all(Rational::is_zero)
+}
+
+/// Determinant `a * d - b * c` of a 2x2 matrix
+fn determinant(a: Rational, b: Rational, c: Rational, d: Rational) -> Result<Rational> {
+    a.checked_mul(d)?.checked_sub(b.checked_mul(c)?)
+}
+
+fn cross2(lhs: &Point<2>, rhs: &Point<2>) -> Result<Rational> {
+    determinant(lhs[0], lhs[1], rhs[0], rhs[1])
+}
+
+fn cross3(lhs: &Point<3>, rhs: &Point<3>) -> Result<Point<3>> {
+    Ok([
+        determinant(lhs[1], lhs[2], rhs[1], rhs[2])?,
+        determinant(lhs[2], lhs[0], rhs[2], rhs[0])?,
+        determinant(lhs[0], lhs[1], rhs[0], rhs[1])?,
+    ])
+}
+
+/// Smallest vector with integer coordinates having the same direction, to keep numbers small
+fn primitive<const D: usize>(v: &Point<D>) -> Result<Point<D>> {
+    let overflow = || eyre!("overflow scaling {v:?} to integers");
+
+    let den_lcm = (v.iter()).try_fold(1, |acc, x| rational::checked_lcm(acc, x.denominator()));
+    let den_lcm = den_lcm.ok_or_else(overflow)?;
+
+    // Coordinates scaled by the common denominator are integers
+    let scaled: [i128; D] = try_point(|i| {
+        let x = v[i];
+        let num = (x.numerator()).checked_mul(den_lcm / x.denominator());
+        Ok(Rational::integer(num.ok_or_else(overflow)?))
+    })?
+    .map(|x| x.numerator());
+
+    let num_gcd = (scaled.iter()).fold(0, |acc, &x| rational::gcd(acc, x));
+
+    Ok(match num_gcd {
+        0 => *v,
+        _ => scaled.map(|x| Rational::integer(x / num_gcd)),
+    })
+}
+
+/// Relative position of two lines
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Intersection {
+    /// The lines meet at parameter `t` of the first line and `u` of the second one
+    Point {
+        t: Rational,
+        u: Rational,
+    },
+    Parallel,
+    Colinear,
+    /// The lines are neither parallel nor coplanar
+    Skew,
+}
+
+/// Parametric line `point + t * direction`
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Line<const D: usize> {
+    pub point: Point<D>,
+    pub direction: Point<D>,
+}
+
+pub type Line2 = Line<2>;
+pub type Line3 = Line<3>;
+
+impl<const D: usize> Line<D> {
+    pub fn new(point: Point<D>, direction: Point<D>) -> Self {
+        Self { point, direction }
+    }
+
+    pub fn from_integers(point: [i64; D], direction: [i64; D]) -> Self {
+        Self::new(point.map(Rational::from), direction.map(Rational::from))
+    }
+
+    pub fn at(&self, t: Rational) -> Result<Point<D>> {
+        try_point(|i| self.point[i].checked_add(t.checked_mul(self.direction[i])?))
+    }
+
+    /// Parameter of a point of the line, or `None` if the point is not on the line
+    pub fn parameter_of(&self, point: &Point<D>) -> Result<Option<Rational>> {
+        let offset = sub(point, &self.point)?;
+        let Some(axis) = (0..D).find(|&i| !self.direction[i].is_zero()) else {
+            return Ok(None);
+        };
+        let t = offset[axis].checked_div(self.direction[axis])?;
+
+        Ok((self.at(t)? == *point).then_some(t))
+    }
+}
+
+impl Line2 {
+    pub fn intersect(&self, other: &Self) -> Result<Intersection> {
+        let offset = sub(&other.point, &self.point)?;
+        let determinant = cross2(&self.direction, &other.direction)?;
+
+        if determinant.is_zero() {
+            return Ok(match cross2(&offset, &self.direction)?.is_zero() {
+                true => Intersection::Colinear,
+                false => Intersection::Parallel,
+            });
+        }
+
+        Ok(Intersection::Point {
+            t: cross2(&offset, &other.direction)?.checked_div(determinant)?,
+            u: cross2(&offset, &self.direction)?.checked_div(determinant)?,
+        })
+    }
+}
+
+impl Line3 {
+    /// Projection of the line on the `(x, y)` plane
+    pub fn project_xy(&self) -> Line2 {
+        Line2::new(
+            [self.point[0], self.point[1]],
+            [self.direction[0], self.direction[1]],
+        )
+    }
+
+    pub fn intersect(&self, other: &Self) -> Result<Intersection> {
+        let offset = sub(&other.point, &self.point)?;
+        let normal = cross3(&self.direction, &other.direction)?;
+
+        if is_zero(&normal) {
+            return Ok(match is_zero(&cross3(&offset, &self.direction)?) {
+                true => Intersection::Colinear,
+                false => Intersection::Parallel,
+            });
+        }
+
+        if !dot(&offset, &normal)?.is_zero() {
+            return Ok(Intersection::Skew);
+        }
+
+        let norm2 = dot(&normal, &normal)?;
+
+        Ok(Intersection::Point {
+            t: dot(&cross3(&offset, &other.direction)?, &normal)?.checked_div(norm2)?,
+            u: dot(&cross3(&offset, &self.direction)?, &normal)?.checked_div(norm2)?,
+        })
+    }
+
+    /// Check if two points moving along the lines, parametrized by time, are at the same position at some time
+    pub fn collides(&self, other: &Self) -> Result<bool> {
+        let offset = sub(&self.point, &other.point)?;
+        let closing_velocity = sub(&other.direction, &self.direction)?;
+
+        // Collision when `offset == t * closing_velocity`
+        Ok(match is_zero(&closing_velocity) {
+            true => is_zero(&offset),
+            false => Line3::new([Rational::ZERO; 3], closing_velocity)
+                .parameter_of(&offset)?
+                .is_some(),
+        })
+    }
+}
+
+/// Find the line of a moving point which collides with the moving points of all the given lines,
+/// each at its own time, where lines are parametrized by time
+pub fn line_hitting_lines(lines: &[Line3]) -> Result<Line3> {
+    let (reference, others) = lines.split_first().value()?;
+
+    // In the frame of the reference point, the solution passes through the origin, so it lies
+    // in the plane containing the origin and each other line
+    let relative = (others.iter())
+        .map(|line| {
+            Ok(Line3::new(
+                sub(&line.point, &reference.point)?,
+                sub(&line.direction, &reference.direction)?,
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let normals = (relative.iter())
+        .map(|line| primitive(&cross3(&line.point, &line.direction)?))
+        .filter_ok(|normal| !is_zero(normal))
+        .collect::<Result<Vec<_>>>()?;
+
+    let direction = (normals.iter().enumerate())
+        .flat_map(|(i, n1)| normals[i + 1..].iter().map(move |n2| cross3(n1, n2)))
+        .filter_ok(|direction| !is_zero(direction))
+        .next()
+        .transpose()?;
+
+    let Some(direction) = direction else {
+        bail!("underdetermined: the lines do not constrain a unique solution");
+    };
+    let direction = primitive(&direction)?;
+
+    // Collision with each other line happens when it crosses the line of the solution through the origin
+    let solution_line = Line3::new([Rational::ZERO; 3], direction);
+
+    let mut collisions = Vec::new();
+    for (line, relative) in iter::zip(others, &relative) {
+        if let Intersection::Point { u, .. } = solution_line.intersect(relative)?
+            && collisions.iter().all(|&(_, t)| t != u)
+        {
+            collisions.push((line.at(u)?, u));
+            if collisions.len() == 2 {
+                break;
+            }
+        }
+    }
+
+    let [(p1, t1), (p2, t2)] = collisions[..] else {
+        bail!("underdetermined: unable to find two collisions at distinct times");
+    };
+
+    let velocity = scale(&sub(&p2, &p1)?, t2.checked_sub(t1)?.recip()?)?;
+    let position = sub(&p1, &scale(&velocity, t1)?)?;
+    let solution = Line3::new(position, velocity);
+
+    for (index, line) in lines.iter().enumerate() {
+        ensure!(
+            solution.collides(line)?,
+            "no solution: line {index} is missed"
+        );
+    }
+
+    Ok(solution)
+}
@@ -0,0 +1,313 @@
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+
+/// Undirected weighted graph with nodes indexed from `0` to `node_count - 1`
+#[derive(Clone, Default)]
+pub struct Graph {
+    adjacency: Vec<Vec<(usize, u64)>>,
+}
+
+/// Cut of a graph, splitting its nodes in two non-empty sides
+pub struct Cut {
+    pub weight: u64,
+    pub side: Vec<bool>,
+    pub edges: Vec<(usize, usize)>,
+}
+
+impl Cut {
+    fn new(graph: &Graph, side: Vec<bool>) -> Self {
+        let edges = graph
+            .edges()
+            .filter(|&(u, v, _)| side[u] != side[v])
+            .collect::<Vec<_>>();
+        let weight = edges.iter().map(|&(_, _, w)| w).sum();
+        let edges = edges.into_iter().map(|(u, v, _)| (u, v)).collect();
+        Self {
+            weight,
+            side,
+            edges,
+        }
+    }
+
+    pub fn partition_sizes(&self) -> (usize, usize) {
+        let size = self.side.iter().filter(|&&x| x).count();
+        (size, self.side.len() - size)
+    }
+}
+
+impl Graph {
+    pub fn new(node_count: usize) -> Self {
+        Self {
+            adjacency: vec![Vec::new(); node_count],
+        }
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.adjacency.len()
+    }
+
+    pub fn add_node(&mut self) -> usize {
+        self.adjacency.push(Vec::new());
+        self.adjacency.len() - 1
+    }
+
+    pub fn add_edge(&mut self, u: usize, v: usize, weight: u64) {
+        self.adjacency[u].push((v, weight));
+        self.adjacency[v].push((u, weight));
+    }
+
+    pub fn neighbors(&self, node: usize) -> &[(usize, u64)] {
+        &self.adjacency[node]
+    }
+
+    /// Iterate over the edges `(u, v, weight)` with `u < v`
+    pub fn edges(&self) -> impl Iterator<Item = (usize, usize, u64)> {
+        self.adjacency.iter().enumerate().flat_map(|(u, links)| {
+            links
+                .iter()
+                .filter(move |&&(v, _)| u < v)
+                .map(move |&(v, weight)| (u, v, weight))
+        })
+    }
+
+    /// Compute a global minimum cut using the Stoer–Wagner algorithm
+    pub fn stoer_wagner(&self) -> Option<Cut> {
+        let node_count = self.node_count();
+        if node_count < 2 {
+            return None;
+        }
+
+        let mut adjacency: Vec<HashMap<usize, u64>> = vec![HashMap::new(); node_count];
+        for (u, v, weight) in self.edges() {
+            *adjacency[u].entry(v).or_default() += weight;
+            *adjacency[v].entry(u).or_default() += weight;
+        }
+
+        let mut members: Vec<Vec<usize>> = (0..node_count).map(|x| vec![x]).collect();
+        let mut active: Vec<usize> = (0..node_count).collect();
+
+        let mut best: Option<(u64, Vec<usize>)> = None;
+
+        let mut weights = vec![0u64; node_count];
+        let mut added = vec![false; node_count];
+        let mut queue = BinaryHeap::new();
+
+        while active.len() > 1 {
+            for &node in &active {
+                weights[node] = 0;
+                added[node] = false;
+            }
+
+            queue.clear();
+            queue.extend(active.iter().map(|&node| (0, node)));
+
+            let mut previous = active[0];
+            let mut last = active[0];
+            let mut cut_of_phase = 0;
+
+            while let Some((weight, node)) = queue.pop() {
+                if added[node] || weight != weights[node] {
+                    continue;
+                }
+
+                added[node] = true;
+                (previous, last, cut_of_phase) = (last, node, weight);
+
+                for (&link, &w) in &adjacency[node] {
+                    if !added[link] {
+                        weights[link] += w;
+                        queue.push((weights[link], link));
+                    }
+                }
+            }
+
+            if best
+                .as_ref()
+                .is_none_or(|(weight, _)| cut_of_phase < *weight)
+            {
+                best = Some((cut_of_phase, members[last].clone()));
+            }
+
+            let last_links = std::mem::take(&mut adjacency[last]);
+            for (link, w) in last_links {
+                adjacency[link].remove(&last);
+                if link != previous {
+                    *adjacency[previous].entry(link).or_default() += w;
+                    *adjacency[link].entry(previous).or_default() += w;
+                }
+            }
+
+            let last_members = std::mem::take(&mut members[last]);
+            members[previous].extend(last_members);
+            active.retain(|&x| x != last);
+        }
+
+        let (_, nodes) = best?;
+        let mut side = vec![false; node_count];
+        for node in nodes {
+            side[node] = true;
+        }
+
+        Some(Cut::new(self, side))
+    }
+
+    /// Compute a minimum cut separating `source` and `sink` using the Edmonds–Karp max-flow algorithm
+    pub fn min_st_cut(&self, source: usize, sink: usize) -> Cut {
+        let mut sources = vec![false; self.node_count()];
+        sources[source] = true;
+
+        let mut network = FlowNetwork::new(self);
+        let (_, reached) = network.max_flow(&sources, sink, u64::MAX);
+        Cut::new(self, reached)
+    }
+
+    /// Compute a global minimum cut with max-flows from node `0` to every other node. Once the minimum
+    /// cut to a sink is known, smaller cuts cannot separate it from node `0`, so it joins the sources.
+    pub fn min_cut_by_max_flow(&self) -> Option<Cut> {
+        let node_count = self.node_count();
+        if node_count < 2 {
+            return None;
+        }
+
+        let mut network = FlowNetwork::new(self);
+        let mut sources = vec![false; node_count];
+        let mut best: Option<(u64, Vec<bool>)> = None;
+
+        // Visit sinks in BFS order so that each one is next to the sources, keeping paths short
+        let mut order = vec![0];
+        let mut visited = vec![false; node_count];
+        visited[0] = true;
+
+        let mut index = 0;
+        while let Some(&node) = order.get(index) {
+            for &(link, _) in self.neighbors(node) {
+                if !visited[link] {
+                    visited[link] = true;
+                    order.push(link);
+                }
+            }
+            index += 1;
+        }
+
+        // Nodes which are not connected to node `0` form a cut of weight zero
+        if let Some(sink) = visited.iter().position(|&x| !x) {
+            sources[0] = true;
+            let (_, side) = network.max_flow(&sources, sink, u64::MAX);
+            return Some(Cut::new(self, side));
+        }
+
+        sources[0] = true;
+        for &sink in &order[1..] {
+            let limit = best.as_ref().map_or(u64::MAX, |&(weight, _)| weight);
+            let (flow, reached) = network.max_flow(&sources, sink, limit);
+            if flow < limit {
+                best = Some((flow, reached));
+            }
+            sources[sink] = true;
+        }
+
+        let (_, side) = best?;
+        Some(Cut::new(self, side))
+    }
+}
+
+/// Residual network of a graph, where each edge is a pair of opposite arcs stored at indices `2k` and `2k + 1`
+struct FlowNetwork {
+    arcs: Vec<usize>,
+    weights: Vec<u64>,
+    capacities: Vec<u64>,
+    adjacency: Vec<Vec<usize>>,
+}
+
+impl FlowNetwork {
+    fn new(graph: &Graph) -> Self {
+        let mut arcs = Vec::new();
+        let mut weights = Vec::new();
+        let mut adjacency = vec![Vec::new(); graph.node_count()];
+
+        for (u, v, weight) in graph.edges() {
+            adjacency[u].push(arcs.len());
+            arcs.push(v);
+            weights.push(weight);
+
+            adjacency[v].push(arcs.len());
+            arcs.push(u);
+            weights.push(weight);
+        }
+
+        Self {
+            arcs,
+            capacities: weights.clone(),
+            weights,
+            adjacency,
+        }
+    }
+
+    /// Push flow from the `sources` to `sink` with the Edmonds–Karp algorithm, stopping once it reaches
+    /// `limit`. Augmenting paths are searched backwards from the sink, which stops as soon as any source
+    /// is found. Returns the flow and the nodes which cannot reach the sink in the final residual network,
+    /// which form the source side of a minimum cut when the flow is below `limit`.
+    fn max_flow(&mut self, sources: &[bool], sink: usize, limit: u64) -> (u64, Vec<bool>) {
+        let node_count = self.adjacency.len();
+
+        self.capacities.copy_from_slice(&self.weights);
+
+        let mut flow = 0;
+        let mut next_arcs = vec![None; node_count];
+        let mut reached = vec![false; node_count];
+        let mut queue = VecDeque::new();
+
+        loop {
+            if flow >= limit {
+                return (flow, reached);
+            }
+
+            next_arcs.fill(None);
+            reached.fill(false);
+            reached[sink] = true;
+
+            queue.clear();
+            queue.push_back(sink);
+
+            let mut start = None;
+
+            while let Some(node) = queue.pop_front() {
+                if sources[node] {
+                    start = Some(node);
+                    break;
+                }
+
+                for &arc in &self.adjacency[node] {
+                    // Arc going from `link` to `node`
+                    let (link, reverse) = (self.arcs[arc], arc ^ 1);
+                    if self.capacities[reverse] > 0 && !reached[link] {
+                        reached[link] = true;
+                        next_arcs[link] = Some(reverse);
+                        queue.push_back(link);
+                    }
+                }
+            }
+
+            let Some(mut node) = start else {
+                return (flow, reached.iter().map(|&x| !x).collect());
+            };
+
+            let mut path = Vec::new();
+            while let Some(arc) = next_arcs[node] {
+                path.push(arc);
+                node = self.arcs[arc];
+            }
+
+            let bottleneck = (path.iter())
+                .map(|&arc| self.capacities[arc])
+                .min()
+                .unwrap_or(0);
+
+            for arc in path {
+                self.capacities[arc] -= bottleneck;
+                self.capacities[arc ^ 1] += bottleneck;
+            }
+
+            flow += bottleneck;
+        }
+    }
+}
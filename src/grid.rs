@@ -0,0 +1,177 @@
+use crate::union_find::UnionFind;
+use crate::*;
+
+use eyre::ensure;
+
+/// One of the 8 symmetries of a square, applied as an optional transposition followed by optional flips
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Orientation {
+    pub transpose: bool,
+    pub flip_x: bool,
+    pub flip_y: bool,
+}
+
+impl Orientation {
+    pub const ALL: [Self; 8] = {
+        let mut all = [Self::new(false, false, false); 8];
+        let mut index = 0;
+        while index < 8 {
+            all[index] = Self::new(index & 4 != 0, index & 2 != 0, index & 1 != 0);
+            index += 1;
+        }
+        all
+    };
+
+    pub const fn new(transpose: bool, flip_x: bool, flip_y: bool) -> Self {
+        Self {
+            transpose,
+            flip_x,
+            flip_y,
+        }
+    }
+}
+
+/// Rectangular grid of cells stored in row-major order
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+    cells: Vec<T>,
+}
+
+impl<T: Copy> Grid<T> {
+    pub fn new(width: usize, height: usize, cells: Vec<T>) -> Result<Self> {
+        ensure!(
+            width * height == cells.len(),
+            "unable to construct Grid: width * height != cells.len()"
+        );
+
+        Ok(Self {
+            width,
+            height,
+            cells,
+        })
+    }
+
+    pub fn filled(width: usize, height: usize, value: T) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![value; width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn cells(&self) -> &[T] {
+        &self.cells
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> T {
+        self.cells[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, value: T) {
+        self.cells[y * self.width + x] = value;
+    }
+
+    pub fn rows(&self) -> impl DoubleEndedIterator<Item = &[T]> {
+        self.cells.chunks_exact(self.width.max(1))
+    }
+
+    pub fn row(&self, y: usize) -> &[T] {
+        &self.cells[y * self.width..(y + 1) * self.width]
+    }
+
+    pub fn column(&self, x: usize) -> impl DoubleEndedIterator<Item = T> + '_ {
+        (0..self.height).map(move |y| self.get(x, y))
+    }
+
+    /// Extract the rectangle of size `width * height` starting at position `(x, y)`
+    pub fn sub_grid(&self, x: usize, y: usize, width: usize, height: usize) -> Self {
+        let cells = (y..y + height)
+            .flat_map(|row| &self.row(row)[x..x + width])
+            .copied()
+            .collect();
+
+        Self {
+            width,
+            height,
+            cells,
+        }
+    }
+
+    /// Copy another grid into this one, starting at position `(x, y)`
+    pub fn paste(&mut self, x: usize, y: usize, other: &Self) {
+        for (row, cells) in other.rows().enumerate() {
+            let start = (y + row) * self.width + x;
+            self.cells[start..start + other.width].copy_from_slice(cells);
+        }
+    }
+
+    pub fn transposed(&self) -> Self {
+        let cells = (0..self.width).flat_map(|x| self.column(x)).collect();
+
+        Self {
+            width: self.height,
+            height: self.width,
+            cells,
+        }
+    }
+
+    pub fn oriented(&self, orientation: Orientation) -> Self {
+        let mut grid = match orientation.transpose {
+            true => self.transposed(),
+            false => self.clone(),
+        };
+
+        if orientation.flip_x {
+            grid.cells
+                .chunks_exact_mut(grid.width.max(1))
+                .for_each(|row| row.reverse());
+        }
+
+        if orientation.flip_y {
+            grid.cells = grid.rows().rev().flatten().copied().collect();
+        }
+
+        grid
+    }
+
+    /// Group the cells matching a predicate into regions of orthogonally adjacent cells
+    pub fn regions(&self, predicate: impl Fn(T) -> bool) -> UnionFind {
+        let mut regions = UnionFind::new(self.cells.len());
+
+        for (index, &cell) in self.cells.iter().enumerate() {
+            if !predicate(cell) {
+                continue;
+            }
+
+            let x = index % self.width;
+
+            if x + 1 < self.width && predicate(self.cells[index + 1]) {
+                regions.union(index, index + 1);
+            }
+            if index + self.width < self.cells.len() && predicate(self.cells[index + self.width]) {
+                regions.union(index, index + self.width);
+            }
+        }
+
+        regions
+    }
+
+    /// Count the regions of orthogonally adjacent cells matching a predicate
+    pub fn count_regions(&self, predicate: impl Fn(T) -> bool) -> usize {
+        let mut regions = self.regions(&predicate);
+
+        (0..self.cells.len())
+            .filter(|&index| predicate(self.cells[index]) && regions.find(index) == index)
+            .count()
+    }
+}
@@ -0,0 +1,254 @@
+use crate::*;
+
+use eyre::ensure;
+use itertools::Itertools;
+
+use std::ops::Range;
+
+/// Set of integers, stored as sorted, disjoint and non-adjacent half-open intervals
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct IntervalSet {
+    intervals: Vec<Range<i64>>,
+}
+
+impl FromIterator<Range<i64>> for IntervalSet {
+    fn from_iter<I: IntoIterator<Item = Range<i64>>>(iter: I) -> Self {
+        let intervals = iter
+            .into_iter()
+            .filter(|range| !range.is_empty())
+            .sorted_unstable_by_key(|range| range.start)
+            .coalesce(|r1, r2| {
+                if r2.start <= r1.end {
+                    Ok(r1.start..r1.end.max(r2.end))
+                } else {
+                    Err((r1, r2))
+                }
+            })
+            .collect();
+
+        Self { intervals }
+    }
+}
+
+impl IntervalSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn intervals(&self) -> &[Range<i64>] {
+        &self.intervals
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    /// Number of integers in the set
+    pub fn total_length(&self) -> i64 {
+        self.intervals
+            .iter()
+            .map(|range| range.end - range.start)
+            .sum()
+    }
+
+    pub fn min(&self) -> Option<i64> {
+        self.intervals.first().map(|range| range.start)
+    }
+
+    pub fn max(&self) -> Option<i64> {
+        self.intervals.last().map(|range| range.end - 1)
+    }
+
+    pub fn contains(&self, x: i64) -> bool {
+        let index = self.intervals.partition_point(|range| range.end <= x);
+        self.intervals
+            .get(index)
+            .is_some_and(|range| range.contains(&x))
+    }
+
+    pub fn insert(&mut self, range: Range<i64>) {
+        *self = self.intervals.iter().cloned().chain([range]).collect();
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        self.intervals
+            .iter()
+            .chain(&other.intervals)
+            .cloned()
+            .collect()
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let mut intervals = Vec::new();
+        let (mut i, mut j) = (0, 0);
+
+        while let (Some(r1), Some(r2)) = (self.intervals.get(i), other.intervals.get(j)) {
+            let range = r1.start.max(r2.start)..r1.end.min(r2.end);
+            if !range.is_empty() {
+                intervals.push(range);
+            }
+
+            if r1.end < r2.end {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+
+        Self { intervals }
+    }
+
+    /// Integers of `bounds` which are not in the set
+    pub fn complement(&self, bounds: Range<i64>) -> Self {
+        let mut intervals = Vec::new();
+        let mut start = bounds.start;
+
+        for range in &self.intervals {
+            if range.start > start {
+                intervals.push(start..range.start.min(bounds.end));
+            }
+            start = start.max(range.end);
+        }
+
+        if start < bounds.end {
+            intervals.push(start..bounds.end);
+        }
+
+        intervals.into_iter().collect()
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        match (self.min(), self.max()) {
+            (Some(min), Some(max)) => self.intersection(&other.complement(min..max + 1)),
+            _ => Self::new(),
+        }
+    }
+}
+
+/// Piecewise-linear map translating each interval of its domain by an offset, and identity elsewhere
+#[derive(Clone, Debug, Default)]
+pub struct IntervalMap {
+    pieces: Vec<(Range<i64>, i64)>,
+}
+
+impl IntervalMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Translate the integers of a source interval by an offset, failing if it overlaps another interval
+    pub fn insert(&mut self, source: Range<i64>, offset: i64) -> Result<()> {
+        if source.is_empty() {
+            return Ok(());
+        }
+
+        let index = self
+            .pieces
+            .partition_point(|(range, _)| range.end <= source.start);
+
+        ensure!(
+            (self.pieces.get(index)).is_none_or(|(range, _)| source.end <= range.start),
+            "overlapping source intervals"
+        );
+
+        self.pieces.insert(index, (source, offset));
+        Ok(())
+    }
+
+    /// Intervals of the domain with their offsets, sorted by start
+    pub fn pieces(&self) -> &[(Range<i64>, i64)] {
+        &self.pieces
+    }
+
+    pub fn get(&self, x: i64) -> i64 {
+        let index = self.pieces.partition_point(|(range, _)| range.end <= x);
+
+        match self.pieces.get(index) {
+            Some((range, offset)) if range.contains(&x) => x + offset,
+            _ => x,
+        }
+    }
+
+    /// Split an interval into consecutive parts with the same offset
+    fn split(&self, range: Range<i64>) -> Vec<(Range<i64>, i64)> {
+        let mut parts = Vec::new();
+        let mut start = range.start;
+
+        let index = self
+            .pieces
+            .partition_point(|(piece, _)| piece.end <= range.start);
+
+        for (piece, offset) in &self.pieces[index..] {
+            if start >= range.end {
+                break;
+            }
+
+            if piece.start > start {
+                parts.push((start..piece.start.min(range.end), 0));
+                start = piece.start;
+            }
+
+            let end = piece.end.min(range.end);
+            if start < end {
+                parts.push((start..end, *offset));
+                start = end;
+            }
+        }
+
+        if start < range.end {
+            parts.push((start..range.end, 0));
+        }
+
+        parts
+    }
+
+    /// Map applying `self` followed by `other`
+    pub fn then(&self, other: &Self) -> Self {
+        let mut pieces = Vec::new();
+
+        for (source, offset) in &self.pieces {
+            let image = source.start + offset..source.end + offset;
+            for (part, other_offset) in other.split(image) {
+                pieces.push((
+                    part.start - offset..part.end - offset,
+                    offset + other_offset,
+                ));
+            }
+        }
+
+        let domain: IntervalSet = self.pieces.iter().map(|(range, _)| range.clone()).collect();
+
+        for (source, offset) in &other.pieces {
+            let uncovered = IntervalSet::from_iter([source.clone()]).difference(&domain);
+            pieces.extend(
+                uncovered
+                    .intervals
+                    .into_iter()
+                    .map(|range| (range, *offset)),
+            );
+        }
+
+        let pieces = pieces
+            .into_iter()
+            .filter(|&(_, offset)| offset != 0)
+            .sorted_unstable_by_key(|(range, _)| range.start)
+            .coalesce(|(r1, o1), (r2, o2)| {
+                if r1.end == r2.start && o1 == o2 {
+                    Ok((r1.start..r2.end, o1))
+                } else {
+                    Err(((r1, o1), (r2, o2)))
+                }
+            })
+            .collect();
+
+        Self { pieces }
+    }
+
+    /// Image of a set of integers
+    pub fn image(&self, set: &IntervalSet) -> IntervalSet {
+        (set.intervals.iter())
+            .flat_map(|range| self.split(range.clone()))
+            .map(|(range, offset)| range.start + offset..range.end + offset)
+            .collect()
+    }
+}
@@ -0,0 +1,361 @@
+use crate::grid::{Grid, Orientation};
+use crate::*;
+
+use eyre::{bail, ensure, eyre};
+use itertools::Itertools;
+
+use std::collections::{HashMap, HashSet};
+
+/// Square image tile, whose borders must match the borders of its neighbors
+pub struct Tile {
+    pub id: u64,
+    pub image: Grid<bool>,
+}
+
+/// Parse tiles separated by empty lines, each starting with a `Tile <id>:` header
+pub fn parse_tiles(input: &str) -> Result<Vec<Tile>> {
+    input
+        .split("\n\n")
+        .filter(|group| !group.trim().is_empty())
+        .map(|group| {
+            let mut lines = group.lines();
+
+            let id = lines
+                .next()
+                .and_then(|x| x.strip_prefix("Tile "))
+                .and_then(|x| x.strip_suffix(':'))
+                .value()?
+                .parse::<u64>()?;
+
+            let rows = lines.collect_vec();
+            let width = rows.first().map_or(0, |row| row.len());
+            ensure!(
+                rows.iter().all(|row| row.len() == width),
+                "tile {id} is not rectangular"
+            );
+
+            let cells = (rows.iter())
+                .flat_map(|row| row.bytes())
+                .map(|x| match x {
+                    b'.' => Ok(false),
+                    b'#' => Ok(true),
+                    _ => bail!("invalid pixel in tile {id}: {:?}", x as char),
+                })
+                .try_collect()?;
+
+            Ok(Tile {
+                id,
+                image: Grid::new(width, rows.len(), cells)?,
+            })
+        })
+        .try_collect()
+}
+
+/// Placement of all tiles in a rectangular layout
+pub struct Arrangement {
+    tile_size: usize,
+    tile_ids: Grid<u64>,
+    full_image: Grid<bool>,
+}
+
+impl Arrangement {
+    pub fn tile_ids(&self) -> &Grid<u64> {
+        &self.tile_ids
+    }
+
+    pub fn corner_ids(&self) -> Vec<u64> {
+        let (width, height) = (self.tile_ids.width(), self.tile_ids.height());
+
+        [
+            (0, 0),
+            (width - 1, 0),
+            (0, height - 1),
+            (width - 1, height - 1),
+        ]
+        .into_iter()
+        .map(|(x, y)| self.tile_ids.get(x, y))
+        .unique()
+        .collect()
+    }
+
+    /// Assembled image, without the borders of each tile
+    pub fn image(&self) -> Grid<bool> {
+        let inner_size = self.tile_size - 2;
+        let (width, height) = (self.tile_ids.width(), self.tile_ids.height());
+
+        let mut image = Grid::filled(width * inner_size, height * inner_size, false);
+
+        for (x, y) in (0..height).flat_map(|y| (0..width).map(move |x| (x, y))) {
+            let tile = self.full_image.sub_grid(
+                x * self.tile_size + 1,
+                y * self.tile_size + 1,
+                inner_size,
+                inner_size,
+            );
+            image.paste(x * inner_size, y * inner_size, &tile);
+        }
+
+        image
+    }
+
+    /// Representation which is identical for all symmetries of the whole arrangement
+    fn canonical_form(&self) -> (Grid<u64>, Grid<bool>) {
+        (Orientation::ALL.into_iter())
+            .map(|orientation| {
+                (
+                    self.tile_ids.oriented(orientation),
+                    self.full_image.oriented(orientation),
+                )
+            })
+            .min()
+            .unwrap_or_else(|| (self.tile_ids.clone(), self.full_image.clone()))
+    }
+}
+
+/// Backtracking assembler of square tiles with arbitrary size, in layouts of any shape
+pub struct Assembler<'a> {
+    tiles: &'a [Tile],
+    tile_size: usize,
+    variants: Vec<Vec<Grid<bool>>>,
+    by_left_border: HashMap<Vec<bool>, Vec<(usize, usize)>>,
+    by_top_border: HashMap<Vec<bool>, Vec<(usize, usize)>>,
+}
+
+impl<'a> Assembler<'a> {
+    pub fn new(tiles: &'a [Tile]) -> Result<Self> {
+        let tile_size = tiles.first().value()?.image.width();
+
+        ensure!(tile_size >= 3, "tiles must have a size of at least 3");
+        ensure!(
+            (tiles.iter())
+                .all(|tile| tile.image.width() == tile_size && tile.image.height() == tile_size),
+            "tiles must be squares of the same size"
+        );
+        ensure!(
+            tiles.iter().map(|tile| tile.id).all_unique(),
+            "tile ids must be unique"
+        );
+
+        let variants = (tiles.iter())
+            .map(|tile| {
+                (Orientation::ALL.into_iter())
+                    .map(|orientation| tile.image.oriented(orientation))
+                    .unique()
+                    .collect_vec()
+            })
+            .collect_vec();
+
+        let mut by_left_border = HashMap::<_, Vec<_>>::new();
+        let mut by_top_border = HashMap::<_, Vec<_>>::new();
+
+        for (index, tile_variants) in variants.iter().enumerate() {
+            for (variant_index, variant) in tile_variants.iter().enumerate() {
+                let left_border = variant.column(0).collect();
+                let top_border = variant.row(0).to_vec();
+
+                by_left_border
+                    .entry(left_border)
+                    .or_default()
+                    .push((index, variant_index));
+                by_top_border
+                    .entry(top_border)
+                    .or_default()
+                    .push((index, variant_index));
+            }
+        }
+
+        Ok(Self {
+            tiles,
+            tile_size,
+            variants,
+            by_left_border,
+            by_top_border,
+        })
+    }
+
+    /// Find the unique arrangement of the tiles, up to symmetries of the whole image
+    pub fn assemble(&self) -> Result<Arrangement> {
+        let mut arrangements = self.arrangements(2);
+
+        match arrangements.len() {
+            0 => bail!("no arrangement found"),
+            1 => arrangements.pop().value(),
+            _ => bail!("ambiguous arrangement: tiles can be assembled in more than one way"),
+        }
+    }
+
+    /// Find up to `limit` distinct arrangements of the tiles, up to symmetries of the whole image
+    pub fn arrangements(&self, limit: usize) -> Vec<Arrangement> {
+        let tile_count = self.tiles.len();
+
+        let mut arrangements = Vec::new();
+        let mut canonical_forms = HashSet::new();
+
+        for width in (1..=tile_count).filter(|&width| tile_count.is_multiple_of(width)) {
+            let mut placed = Vec::with_capacity(tile_count);
+            let mut used = vec![false; tile_count];
+
+            let mut search = Search {
+                width,
+                placed: &mut placed,
+                used: &mut used,
+                arrangements: &mut arrangements,
+                canonical_forms: &mut canonical_forms,
+                limit,
+            };
+
+            if self.search(&mut search) {
+                break;
+            }
+        }
+
+        arrangements
+    }
+
+    fn variant(&self, (index, variant_index): (usize, usize)) -> &Grid<bool> {
+        &self.variants[index][variant_index]
+    }
+
+    /// Place the next tile, returning `true` if the search is complete
+    fn search(&self, search: &mut Search<'_>) -> bool {
+        let position = search.placed.len();
+
+        if position == self.tiles.len() {
+            let arrangement = self.build_arrangement(search.width, search.placed);
+            if search.canonical_forms.insert(arrangement.canonical_form()) {
+                search.arrangements.push(arrangement);
+            }
+            return search.arrangements.len() >= search.limit;
+        }
+
+        let (x, y) = (position % search.width, position / search.width);
+        let last = self.tile_size - 1;
+
+        let above = (y > 0).then(|| self.variant(search.placed[position - search.width]));
+
+        let candidates = if x > 0 {
+            let left = self.variant(search.placed[position - 1]);
+            let border = left.column(last).collect_vec();
+            self.by_left_border
+                .get(&border)
+                .cloned()
+                .unwrap_or_default()
+        } else if let Some(above) = above {
+            self.by_top_border
+                .get(above.row(last))
+                .cloned()
+                .unwrap_or_default()
+        } else {
+            (self.variants.iter())
+                .enumerate()
+                .flat_map(|(index, variants)| (0..variants.len()).map(move |x| (index, x)))
+                .collect()
+        };
+
+        for candidate in candidates {
+            if search.used[candidate.0] {
+                continue;
+            }
+
+            if above.is_some_and(|above| above.row(last) != self.variant(candidate).row(0)) {
+                continue;
+            }
+
+            search.used[candidate.0] = true;
+            search.placed.push(candidate);
+
+            let done = self.search(search);
+
+            search.placed.pop();
+            search.used[candidate.0] = false;
+
+            if done {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    fn build_arrangement(&self, width: usize, placed: &[(usize, usize)]) -> Arrangement {
+        let height = placed.len() / width;
+
+        let mut tile_ids = Grid::filled(width, height, 0);
+        let mut full_image = Grid::filled(width * self.tile_size, height * self.tile_size, false);
+
+        for (position, &candidate) in placed.iter().enumerate() {
+            let (x, y) = (position % width, position / width);
+            tile_ids.set(x, y, self.tiles[candidate.0].id);
+            full_image.paste(
+                x * self.tile_size,
+                y * self.tile_size,
+                self.variant(candidate),
+            );
+        }
+
+        Arrangement {
+            tile_size: self.tile_size,
+            tile_ids,
+            full_image,
+        }
+    }
+}
+
+struct Search<'a> {
+    width: usize,
+    placed: &'a mut Vec<(usize, usize)>,
+    used: &'a mut Vec<bool>,
+    arrangements: &'a mut Vec<Arrangement>,
+    canonical_forms: &'a mut HashSet<(Grid<u64>, Grid<bool>)>,
+    limit: usize,
+}
+
+/// Pattern made of the `#` pixels of a text, where other pixels can match anything
+pub struct Pattern {
+    width: usize,
+    height: usize,
+    offsets: Vec<(usize, usize)>,
+}
+
+impl Pattern {
+    pub fn parse(text: &str) -> Result<Self> {
+        let lines = text.lines().collect_vec();
+
+        let offsets = (lines.iter().enumerate())
+            .flat_map(|(y, line)| line.bytes().enumerate().map(move |(x, c)| (x, y, c)))
+            .filter_map(|(x, y, c)| match c {
+                b'#' => Some(Ok((x, y))),
+                b' ' | b'.' => None,
+                _ => Some(Err(eyre!("invalid pattern pixel: {:?}", c as char))),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        ensure!(!offsets.is_empty(), "empty pattern");
+
+        Ok(Self {
+            width: offsets.iter().map(|&(x, _)| x + 1).max().unwrap_or(0),
+            height: offsets.iter().map(|&(_, y)| y + 1).max().unwrap_or(0),
+            offsets,
+        })
+    }
+
+    pub fn pixel_count(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Positions of the pixels of the pattern placed at `(x, y)`
+    pub fn pixels_at(&self, x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.offsets.iter().map(move |&(dx, dy)| (x + dx, y + dy))
+    }
+
+    /// Find all positions of the top-left corner of the pattern in an image
+    pub fn find_in(&self, image: &Grid<bool>) -> Vec<(usize, usize)> {
+        let max_x = (image.width() + 1).saturating_sub(self.width);
+        let max_y = (image.height() + 1).saturating_sub(self.height);
+
+        (0..max_y)
+            .flat_map(|y| (0..max_x).map(move |x| (x, y)))
+            .filter(|&(x, y)| self.pixels_at(x, y).all(|(x, y)| image.get(x, y)))
+            .collect()
+    }
+}
@@ -0,0 +1,156 @@
+use crate::grid::Grid;
+use crate::*;
+
+use std::fmt::{self, Display};
+use std::num::NonZeroUsize;
+use std::panic;
+use std::thread;
+
+const LIST_SIZE: usize = 256;
+const ROUNDS: usize = 64;
+const LENGTH_SUFFIX: [u8; 5] = [17, 31, 73, 47, 23];
+const DIGEST_BITS: usize = 128;
+
+/// Circular list of the sparse hash, twisted by a sequence of lengths
+#[derive(Clone)]
+pub struct KnotList {
+    list: Vec<u8>,
+    position: usize,
+    skip: usize,
+}
+
+impl Default for KnotList {
+    fn default() -> Self {
+        Self::new(LIST_SIZE)
+    }
+}
+
+impl KnotList {
+    pub fn new(size: usize) -> Self {
+        Self {
+            list: (0..size).map(|x| x as u8).collect(),
+            position: 0,
+            skip: 0,
+        }
+    }
+
+    pub fn list(&self) -> &[u8] {
+        &self.list
+    }
+
+    /// Reverse the sublist of length `len` starting at the current position
+    pub fn twist(&mut self, len: usize) {
+        let size = self.list.len();
+
+        if len >= 2 {
+            let offset = self.position % size;
+            self.list.rotate_left(offset);
+            self.list[..len].reverse();
+            self.list.rotate_right(offset);
+        }
+
+        self.position += len + self.skip;
+        self.skip += 1;
+    }
+
+    pub fn round(&mut self, lengths: impl IntoIterator<Item = usize>) {
+        for len in lengths {
+            self.twist(len);
+        }
+    }
+}
+
+/// Dense knot hash
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Digest(pub [u8; 16]);
+
+impl Digest {
+    pub fn to_hex(&self) -> String {
+        self.to_string()
+    }
+
+    /// Bits of the hash, from the most significant bit of the first byte
+    pub fn bits(&self) -> impl Iterator<Item = bool> + '_ {
+        (self.0.iter()).flat_map(|&byte| (0..8).rev().map(move |bit| (byte >> bit) & 1 != 0))
+    }
+}
+
+impl Display for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|byte| write!(f, "{byte:02x}"))
+    }
+}
+
+/// Streaming knot hasher, which can be cloned to hash several messages sharing a common prefix
+#[derive(Clone, Default)]
+pub struct KnotHasher {
+    input: Vec<u8>,
+}
+
+impl KnotHasher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(&mut self, data: &[u8]) -> &mut Self {
+        self.input.extend_from_slice(data);
+        self
+    }
+
+    pub fn finalize(&self) -> Digest {
+        let lengths = self.input.iter().chain(&LENGTH_SUFFIX).map(|&x| x as usize);
+
+        let mut list = KnotList::default();
+        for _ in 0..ROUNDS {
+            list.round(lengths.clone());
+        }
+
+        let mut digest = [0; 16];
+        for (byte, block) in digest.iter_mut().zip(list.list().chunks_exact(16)) {
+            *byte = block.iter().fold(0, |acc, x| acc ^ x);
+        }
+
+        Digest(digest)
+    }
+}
+
+pub fn knot_hash(input: &[u8]) -> Digest {
+    KnotHasher::new().update(input).finalize()
+}
+
+/// Hash several messages, split across all available threads
+pub fn knot_hash_batch<I: AsRef<[u8]> + Sync>(inputs: &[I]) -> Vec<Digest> {
+    let thread_count = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+    let chunk_size = inputs.len().div_ceil(thread_count).max(1);
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = inputs
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(|| {
+                    (chunk.iter())
+                        .map(|input| knot_hash(input.as_ref()))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+            .collect()
+    })
+}
+
+/// Grid of the bits of the hashes of `key-0` to `key-<rows - 1>`, one hash per row
+pub fn bit_grid(key: &[u8], rows: usize) -> Result<Grid<bool>> {
+    let inputs = (0..rows)
+        .map(|row| [key, format!("-{row}").as_bytes()].concat())
+        .collect::<Vec<_>>();
+
+    let cells = (knot_hash_batch(&inputs).iter())
+        .flat_map(|digest| digest.bits())
+        .collect();
+
+    Grid::new(DIGEST_BITS, rows, cells)
+}
@@ -1,24 +1,75 @@
+pub mod boxes;
+pub mod dag;
+pub mod dot;
+pub mod geometry;
+pub mod graph;
+pub mod grid;
+pub mod interval;
+pub mod jigsaw;
+pub mod knot_hash;
+pub mod md5mine;
+pub mod nonogram;
+pub mod rational;
+pub mod rotation;
+pub mod schedule;
+pub mod union_find;
+
 use eyre::{Report, eyre};
 use itertools::ProcessResults;
 
 use std::env;
+use std::error::Error;
 use std::fs;
 use std::iter::Sum;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 pub type Result<T> = eyre::Result<T>;
 
+/// Read the puzzle input from `inputs/<bin>.txt`, or from the file given with `--input <path>`
 pub fn setup(input_file: &str) -> Result<Vec<u8>> {
     // SAFETY: program is single-thread
     unsafe { env::set_var("RUST_BACKTRACE", "full") };
 
     color_eyre::install().unwrap_or_default();
 
-    let mut path = Path::new("inputs").join(Path::new(input_file).file_stem().value()?);
-    path.set_extension("txt");
+    let path = match option::<PathBuf>("--input")? {
+        Some(path) => path,
+        None => {
+            let mut path = Path::new("inputs").join(Path::new(input_file).file_stem().value()?);
+            path.set_extension("txt");
+            path
+        }
+    };
+
     Ok(fs::read(path)?)
 }
 
+/// Check if a command-line flag (e.g. `--dot`) was passed to the program
+pub fn flag(name: &str) -> bool {
+    env::args().skip(1).any(|arg| arg == name)
+}
+
+/// Parse the value following a command-line option (e.g. `--row 10`)
+pub fn option<T>(name: &str) -> Result<Option<T>>
+where
+    T: FromStr,
+    T::Err: Error + Send + Sync + 'static,
+{
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        if arg == name {
+            let value = args
+                .next()
+                .ok_or_else(|| eyre!("missing value for {name}"))?;
+            return Ok(Some(value.parse()?));
+        }
+    }
+
+    Ok(None)
+}
+
 pub trait OptionExt<T> {
     fn value(self) -> Result<T>;
 }
@@ -0,0 +1,142 @@
+use md5::{Context, Digest};
+
+use std::collections::VecDeque;
+use std::num::NonZeroUsize;
+use std::panic;
+use std::thread;
+
+/// Number of MD5 computations done by each thread per batch
+const BATCH_WORK: usize = 1 << 14;
+
+/// MD5 hasher for messages sharing a common prefix
+#[derive(Clone)]
+pub struct PrefixHasher {
+    context: Context,
+}
+
+impl PrefixHasher {
+    pub fn new(prefix: &[u8]) -> Self {
+        let mut context = Context::new();
+        context.consume(prefix);
+        Self { context }
+    }
+
+    pub fn hash(&self, suffix: &[u8]) -> Digest {
+        let mut context = self.context.clone();
+        context.consume(suffix);
+        context.finalize()
+    }
+
+    /// Hash the prefix followed by the decimal representation of an index
+    pub fn hash_index(&self, index: usize) -> Digest {
+        let mut buf = [0u8; 20];
+        let mut pos = buf.len();
+        let mut n = index;
+
+        loop {
+            pos -= 1;
+            buf[pos] = b'0' + (n % 10) as u8;
+            n /= 10;
+            if n == 0 {
+                break;
+            }
+        }
+
+        self.hash(&buf[pos..])
+    }
+}
+
+/// Rehash a digest as its lowercase hexadecimal representation
+pub fn stretch(mut digest: Digest, rounds: usize) -> Digest {
+    const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+    let mut hex = [0u8; 32];
+
+    for _ in 0..rounds {
+        for (chunk, byte) in hex.chunks_exact_mut(2).zip(digest.0) {
+            chunk[0] = HEX_DIGITS[(byte >> 4) as usize];
+            chunk[1] = HEX_DIGITS[(byte & 0x0F) as usize];
+        }
+        digest = md5::compute(hex);
+    }
+
+    digest
+}
+
+/// Iterator over the indices and digests of `prefix + index` matching a filter, in increasing index order.
+///
+/// Consecutive indices are hashed in batches split across all available threads.
+pub struct Miner<F> {
+    hasher: PrefixHasher,
+    filter: F,
+    stretch_rounds: usize,
+    next_index: usize,
+    thread_count: usize,
+    found: VecDeque<(usize, Digest)>,
+}
+
+impl<F: Fn(&Digest) -> bool + Sync> Miner<F> {
+    pub fn new(prefix: &[u8], start_index: usize, filter: F) -> Self {
+        let thread_count = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+
+        Self {
+            hasher: PrefixHasher::new(prefix),
+            filter,
+            stretch_rounds: 0,
+            next_index: start_index,
+            thread_count,
+            found: VecDeque::new(),
+        }
+    }
+
+    /// Apply key stretching to each digest before filtering it
+    pub fn with_stretching(mut self, rounds: usize) -> Self {
+        self.stretch_rounds = rounds;
+        self
+    }
+
+    fn mine_batch(&mut self) {
+        let batch_size = (BATCH_WORK / (self.stretch_rounds + 1)).max(1);
+        let start_index = self.next_index;
+
+        let (hasher, filter, stretch_rounds) = (&self.hasher, &self.filter, self.stretch_rounds);
+
+        let mine_range = |start: usize| {
+            (start..start + batch_size)
+                .map(|index| (index, stretch(hasher.hash_index(index), stretch_rounds)))
+                .filter(|(_, digest)| filter(digest))
+                .collect::<Vec<_>>()
+        };
+
+        if self.thread_count == 1 {
+            self.found.extend(mine_range(start_index));
+        } else {
+            thread::scope(|scope| {
+                let handles: Vec<_> = (0..self.thread_count)
+                    .map(|i_thread| {
+                        let start = start_index + i_thread * batch_size;
+                        scope.spawn(move || mine_range(start))
+                    })
+                    .collect();
+
+                for handle in handles {
+                    self.found
+                        .extend(handle.join().unwrap_or_else(|e| panic::resume_unwind(e)));
+                }
+            });
+        }
+
+        self.next_index += self.thread_count * batch_size;
+    }
+}
+
+impl<F: Fn(&Digest) -> bool + Sync> Iterator for Miner<F> {
+    type Item = (usize, Digest);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.found.is_empty() {
+            self.mine_batch();
+        }
+        self.found.pop_front()
+    }
+}
@@ -0,0 +1,368 @@
+use crate::grid::Grid;
+use crate::*;
+
+use eyre::{bail, ensure, eyre};
+use itertools::Itertools;
+
+use std::iter;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Cell {
+    Unknown,
+    Filled,
+    Empty,
+}
+
+impl Cell {
+    pub fn parse(x: u8) -> Result<Self> {
+        match x {
+            b'?' => Ok(Self::Unknown),
+            b'#' => Ok(Self::Filled),
+            b'.' => Ok(Self::Empty),
+            _ => bail!("unknown cell state: {:?}", x as char),
+        }
+    }
+
+    pub fn to_char(self) -> char {
+        match self {
+            Self::Unknown => '?',
+            Self::Filled => '#',
+            Self::Empty => '.',
+        }
+    }
+}
+
+/// Sizes of the groups of filled cells of a line, in order
+pub type Clues = Vec<usize>;
+
+/// Row or column of a nonogram, whose filled cells form consecutive groups with the sizes of the clues
+#[derive(Clone, Debug)]
+pub struct Line {
+    cells: Vec<Cell>,
+    clues: Clues,
+    empty_prefix_counts: Vec<usize>,
+}
+
+impl Line {
+    pub fn new(cells: Vec<Cell>, clues: Clues) -> Self {
+        let empty_prefix_counts = iter::once(0)
+            .chain(cells.iter().scan(0, |count, &cell| {
+                *count += usize::from(cell == Cell::Empty);
+                Some(*count)
+            }))
+            .collect();
+
+        Self {
+            cells,
+            clues,
+            empty_prefix_counts,
+        }
+    }
+
+    /// Parse a line of cells followed by comma-separated clues (e.g. `???.### 1,1,3`)
+    pub fn parse(text: &str) -> Result<Self> {
+        let (cells, clues) = text.split_ascii_whitespace().next_tuple().value()?;
+
+        let cells = cells.bytes().map(Cell::parse).try_collect()?;
+        let clues = clues.split(',').map(|x| x.parse()).try_collect()?;
+
+        Ok(Self::new(cells, clues))
+    }
+
+    pub fn cells(&self) -> &[Cell] {
+        &self.cells
+    }
+
+    pub fn clues(&self) -> &[usize] {
+        &self.clues
+    }
+
+    /// Repeat the line `factor` times, with unknown cells between copies
+    pub fn unfold(&self, factor: usize) -> Self {
+        let cells = iter::repeat_n(&self.cells, factor)
+            .flat_map(|cells| iter::once(Cell::Unknown).chain(cells.iter().copied()))
+            .skip(1)
+            .collect();
+
+        Self::new(cells, self.clues.repeat(factor))
+    }
+
+    /// Check if the group of a clue can start at `start`, followed by an empty cell or the end of the line
+    fn fits(&self, start: usize, size: usize) -> bool {
+        let end = start + size;
+
+        end <= self.cells.len()
+            && self.empty_prefix_counts[end] == self.empty_prefix_counts[start]
+            && self.cells.get(end) != Some(&Cell::Filled)
+    }
+
+    /// Position following a group of `size` cells starting at `start` and its separator
+    fn after_group(&self, start: usize, size: usize) -> usize {
+        (start + size + 1).min(self.cells.len())
+    }
+
+    /// Table of the number of ways to complete the cells from position `i` with the clues from index `g`,
+    /// stored at index `i * (clues + 1) + g`
+    fn suffix_counts(&self) -> Vec<u128> {
+        let (len, clue_count) = (self.cells.len(), self.clues.len());
+        let stride = clue_count + 1;
+
+        let mut counts = vec![0; (len + 1) * stride];
+        counts[len * stride + clue_count] = 1;
+
+        for i in (0..len).rev() {
+            for g in 0..=clue_count {
+                let mut count = 0;
+
+                if self.cells[i] != Cell::Filled {
+                    count += counts[(i + 1) * stride + g];
+                }
+
+                if let Some(&size) = self.clues.get(g)
+                    && self.fits(i, size)
+                {
+                    count += counts[self.after_group(i, size) * stride + g + 1];
+                }
+
+                counts[i * stride + g] = count;
+            }
+        }
+
+        counts
+    }
+
+    /// Table of the number of ways to fill the cells before position `i` with the clues before index `g`
+    fn prefix_counts(&self) -> Vec<u128> {
+        let (len, clue_count) = (self.cells.len(), self.clues.len());
+        let stride = clue_count + 1;
+
+        let mut counts = vec![0; (len + 1) * stride];
+        counts[0] = 1;
+
+        for i in 0..len {
+            for g in 0..=clue_count {
+                let count = counts[i * stride + g];
+                if count == 0 {
+                    continue;
+                }
+
+                if self.cells[i] != Cell::Filled {
+                    counts[(i + 1) * stride + g] += count;
+                }
+
+                if let Some(&size) = self.clues.get(g)
+                    && self.fits(i, size)
+                {
+                    counts[self.after_group(i, size) * stride + g + 1] += count;
+                }
+            }
+        }
+
+        counts
+    }
+
+    /// Number of arrangements of the groups compatible with the known cells
+    pub fn count(&self) -> u128 {
+        self.suffix_counts()[0]
+    }
+
+    /// Arrangement with the given index in lexicographic order (empty before filled), as filled flags
+    pub fn arrangement(&self, index: u128) -> Option<Vec<bool>> {
+        self.unrank(&self.suffix_counts(), index)
+    }
+
+    /// Enumerate all arrangements in lexicographic order (empty before filled)
+    pub fn arrangements(&self) -> impl Iterator<Item = Vec<bool>> + '_ {
+        let counts = self.suffix_counts();
+        (0..counts[0]).map_while(move |index| self.unrank(&counts, index))
+    }
+
+    fn unrank(&self, counts: &[u128], mut index: u128) -> Option<Vec<bool>> {
+        let (len, stride) = (self.cells.len(), self.clues.len() + 1);
+
+        if index >= counts[0] {
+            return None;
+        }
+
+        let mut arrangement = Vec::with_capacity(len);
+        let (mut i, mut g) = (0, 0);
+
+        while i < len {
+            if self.cells[i] != Cell::Filled {
+                let count = counts[(i + 1) * stride + g];
+                if index < count {
+                    arrangement.push(false);
+                    i += 1;
+                    continue;
+                }
+                index -= count;
+            }
+
+            let size = *self.clues.get(g)?;
+            if !self.fits(i, size) {
+                return None;
+            }
+
+            let next = self.after_group(i, size);
+            let count = counts[next * stride + g + 1];
+            if index >= count {
+                return None;
+            }
+
+            arrangement.extend(iter::repeat_n(true, size));
+            arrangement.extend(iter::repeat_n(false, next - i - size));
+            (i, g) = (next, g + 1);
+        }
+
+        Some(arrangement)
+    }
+
+    /// Deduce the cells which are identical in all arrangements, or `None` if there is no arrangement
+    pub fn solve(&self) -> Option<Vec<Cell>> {
+        let (len, clue_count) = (self.cells.len(), self.clues.len());
+        let stride = clue_count + 1;
+
+        let suffix_counts = self.suffix_counts();
+        if suffix_counts[0] == 0 {
+            return None;
+        }
+
+        let prefix_counts = self.prefix_counts();
+
+        let mut can_be_empty = vec![false; len];
+        let mut filled_coverage = vec![0i64; len + 1];
+
+        for i in 0..len {
+            for g in 0..=clue_count {
+                if prefix_counts[i * stride + g] == 0 {
+                    continue;
+                }
+
+                if self.cells[i] != Cell::Filled && suffix_counts[(i + 1) * stride + g] > 0 {
+                    can_be_empty[i] = true;
+                }
+
+                if let Some(&size) = self.clues.get(g)
+                    && self.fits(i, size)
+                    && suffix_counts[self.after_group(i, size) * stride + g + 1] > 0
+                {
+                    filled_coverage[i] += 1;
+                    filled_coverage[i + size] -= 1;
+                    if i + size < len {
+                        can_be_empty[i + size] = true;
+                    }
+                }
+            }
+        }
+
+        let can_be_filled = filled_coverage.iter().scan(0, |coverage, &delta| {
+            *coverage += delta;
+            Some(*coverage > 0)
+        });
+
+        let cells = iter::zip(can_be_filled, can_be_empty)
+            .map(|(filled, empty)| match (filled, empty) {
+                (true, false) => Cell::Filled,
+                (false, true) => Cell::Empty,
+                _ => Cell::Unknown,
+            })
+            .collect();
+
+        Some(cells)
+    }
+}
+
+/// Parse the clues of the rows, then the clues of the columns, as two blocks separated by an empty line,
+/// with one line of comma-separated sizes per row or column (`0` for an empty line)
+pub fn parse_clues(text: &str) -> Result<(Vec<Clues>, Vec<Clues>)> {
+    let (rows, columns) = text.split("\n\n").next_tuple().value()?;
+
+    let parse_block = |block: &str| -> Result<Vec<Clues>> {
+        let clues = block
+            .lines()
+            .map(|line| {
+                (line.split(',').map(|x| x.trim().parse::<usize>()))
+                    .filter_ok(|&size| size > 0)
+                    .try_collect()
+            })
+            .try_collect()?;
+
+        Ok(clues)
+    };
+
+    Ok((parse_block(rows)?, parse_block(columns)?))
+}
+
+/// Solve a nonogram grid by propagating the constraints of the rows and columns,
+/// guessing a cell when propagation is not enough
+pub fn solve_grid(row_clues: &[Clues], column_clues: &[Clues]) -> Result<Grid<Cell>> {
+    let grid = Grid::filled(column_clues.len(), row_clues.len(), Cell::Unknown);
+
+    let mut solutions = Vec::new();
+    search(grid, row_clues, column_clues, &mut solutions);
+
+    ensure!(solutions.len() < 2, "ambiguous nonogram: several solutions");
+    solutions
+        .pop()
+        .ok_or_else(|| eyre!("nonogram has no solution"))
+}
+
+/// Find up to two solutions, to detect ambiguous grids
+fn search(
+    mut grid: Grid<Cell>,
+    row_clues: &[Clues],
+    column_clues: &[Clues],
+    solutions: &mut Vec<Grid<Cell>>,
+) {
+    if propagate(&mut grid, row_clues, column_clues).is_none() {
+        return;
+    }
+
+    let Some(position) = grid.cells().iter().position(|&cell| cell == Cell::Unknown) else {
+        solutions.push(grid);
+        return;
+    };
+
+    let (x, y) = (position % grid.width(), position / grid.width());
+
+    for guess in [Cell::Filled, Cell::Empty] {
+        if solutions.len() >= 2 {
+            return;
+        }
+
+        let mut guessed = grid.clone();
+        guessed.set(x, y, guess);
+        search(guessed, row_clues, column_clues, solutions);
+    }
+}
+
+/// Solve lines until a fixed point is reached, returning `None` on contradiction
+fn propagate(grid: &mut Grid<Cell>, row_clues: &[Clues], column_clues: &[Clues]) -> Option<()> {
+    loop {
+        let mut changed = false;
+
+        for (y, clues) in row_clues.iter().enumerate() {
+            let line = Line::new(grid.row(y).to_vec(), clues.clone());
+            for (x, cell) in line.solve()?.into_iter().enumerate() {
+                if cell != grid.get(x, y) {
+                    grid.set(x, y, cell);
+                    changed = true;
+                }
+            }
+        }
+
+        for (x, clues) in column_clues.iter().enumerate() {
+            let line = Line::new(grid.column(x).collect(), clues.clone());
+            for (y, cell) in line.solve()?.into_iter().enumerate() {
+                if cell != grid.get(x, y) {
+                    grid.set(x, y, cell);
+                    changed = true;
+                }
+            }
+        }
+
+        if !changed {
+            return Some(());
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use crate::*;
+
+use eyre::{ensure, eyre};
+
+use std::cmp::Ordering;
+use std::fmt::{self, Display};
+
+/// Exact fraction, always stored in lowest terms with a positive denominator.
+/// Arithmetic is checked: overflowing the `i128` numerator or denominator is an error.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Rational {
+    num: i128,
+    den: i128,
+}
+
+pub fn gcd(mut x: i128, mut y: i128) -> i128 {
+    while y != 0 {
+        (x, y) = (y, x % y);
+    }
+    x.abs()
+}
+
+/// Least common multiple of two positive integers, or `None` on overflow
+pub fn checked_lcm(x: i128, y: i128) -> Option<i128> {
+    (x / gcd(x, y)).checked_mul(y)
+}
+
+impl Rational {
+    pub const ZERO: Self = Self::integer(0);
+    pub const ONE: Self = Self::integer(1);
+
+    pub fn new(num: i128, den: i128) -> Result<Self> {
+        ensure!(den != 0, "zero denominator in {num}/{den}");
+
+        let divisor = gcd(num, den) * den.signum();
+        Ok(Self {
+            num: num / divisor,
+            den: den / divisor,
+        })
+    }
+
+    pub const fn integer(x: i128) -> Self {
+        Self { num: x, den: 1 }
+    }
+
+    pub fn numerator(&self) -> i128 {
+        self.num
+    }
+
+    pub fn denominator(&self) -> i128 {
+        self.den
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.num == 0
+    }
+
+    pub fn to_integer(&self) -> Option<i128> {
+        (self.den == 1).then_some(self.num)
+    }
+
+    pub fn recip(self) -> Result<Self> {
+        ensure!(!self.is_zero(), "division by zero");
+        Self::new(self.den, self.num)
+    }
+
+    pub fn checked_neg(self) -> Result<Self> {
+        let num = (self.num.checked_neg()).ok_or_else(|| eyre!("overflow computing -{self}"))?;
+        Ok(Self { num, den: self.den })
+    }
+
+    pub fn checked_add(self, rhs: Self) -> Result<Self> {
+        let sum = checked_lcm(self.den, rhs.den).and_then(|den| {
+            let lhs_num = self.num.checked_mul(den / self.den)?;
+            let rhs_num = rhs.num.checked_mul(den / rhs.den)?;
+            Some((lhs_num.checked_add(rhs_num)?, den))
+        });
+
+        let (num, den) = sum.ok_or_else(|| eyre!("overflow computing {self} + {rhs}"))?;
+        Self::new(num, den)
+    }
+
+    pub fn checked_sub(self, rhs: Self) -> Result<Self> {
+        self.checked_add(rhs.checked_neg()?)
+    }
+
+    pub fn checked_mul(self, rhs: Self) -> Result<Self> {
+        // Cross-reducing first keeps the products as small as possible
+        let (g1, g2) = (gcd(self.num, rhs.den).max(1), gcd(rhs.num, self.den).max(1));
+
+        let product = ((self.num / g1).checked_mul(rhs.num / g2))
+            .zip((self.den / g2).checked_mul(rhs.den / g1));
+
+        let (num, den) = product.ok_or_else(|| eyre!("overflow computing {self} * {rhs}"))?;
+        Self::new(num, den)
+    }
+
+    pub fn checked_div(self, rhs: Self) -> Result<Self> {
+        self.checked_mul(rhs.recip()?)
+    }
+}
+
+impl From<i64> for Rational {
+    fn from(x: i64) -> Self {
+        Self::integer(x.into())
+    }
+}
+
+impl Ord for Rational {
+    /// Compare the integer parts, then the fractional parts through their reciprocals,
+    /// which never overflows unlike cross-multiplication
+    fn cmp(&self, other: &Self) -> Ordering {
+        let (mut lhs, mut rhs) = ((self.num, self.den), (other.num, other.den));
+
+        loop {
+            let (lhs_floor, rhs_floor) = (lhs.0.div_euclid(lhs.1), rhs.0.div_euclid(rhs.1));
+            if lhs_floor != rhs_floor {
+                return lhs_floor.cmp(&rhs_floor);
+            }
+
+            let (lhs_rem, rhs_rem) = (lhs.0.rem_euclid(lhs.1), rhs.0.rem_euclid(rhs.1));
+            match (lhs_rem, rhs_rem) {
+                (0, 0) => return Ordering::Equal,
+                (0, _) => return Ordering::Less,
+                (_, 0) => return Ordering::Greater,
+                // A larger fractional part has a smaller reciprocal
+                _ => (lhs, rhs) = ((rhs.1, rhs_rem), (lhs.1, lhs_rem)),
+            }
+        }
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.den {
+            1 => write!(f, "{}", self.num),
+            _ => write!(f, "{}/{}", self.num, self.den),
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use itertools::Itertools;
+
+pub type Vec3 = [i64; 3];
+pub type Mat3x3 = [Vec3; 3];
+
+pub trait Vector {
+    fn add(&self, rhs: &Self) -> Self;
+    fn sub(&self, rhs: &Self) -> Self;
+    fn abs(&self) -> Self;
+    fn scale(&self, factor: i64) -> Self;
+    fn norm(&self) -> i64;
+    fn norm2(&self) -> i64;
+    fn dot(&self, rhs: &Self) -> i64;
+    fn apply(&self, matrix: &Mat3x3) -> Self;
+}
+
+impl Vector for Vec3 {
+    fn add(&self, rhs: &Self) -> Self {
+        [self[0] + rhs[0], self[1] + rhs[1], self[2] + rhs[2]]
+    }
+
+    fn sub(&self, rhs: &Self) -> Self {
+        [self[0] - rhs[0], self[1] - rhs[1], self[2] - rhs[2]]
+    }
+
+    fn abs(&self) -> Self {
+        [self[0].abs(), self[1].abs(), self[2].abs()]
+    }
+
+    fn scale(&self, factor: i64) -> Self {
+        [factor * self[0], factor * self[1], factor * self[2]]
+    }
+
+    fn norm(&self) -> i64 {
+        self[0].abs() + self[1].abs() + self[2].abs()
+    }
+
+    fn norm2(&self) -> i64 {
+        self[0] * self[0] + self[1] * self[1] + self[2] * self[2]
+    }
+
+    fn dot(&self, rhs: &Self) -> i64 {
+        self[0] * rhs[0] + self[1] * rhs[1] + self[2] * rhs[2]
+    }
+
+    fn apply(&self, matrix: &Mat3x3) -> Self {
+        [
+            self.dot(&matrix[0]),
+            self.dot(&matrix[1]),
+            self.dot(&matrix[2]),
+        ]
+    }
+}
+
+pub trait Matrix {
+    fn identity() -> Self;
+    fn matmul(&self, rhs: &Self) -> Self;
+    fn transpose(&self) -> Self;
+    fn column(&self, index: usize) -> Vec3;
+    fn determinant(&self) -> i64;
+}
+
+impl Matrix for Mat3x3 {
+    fn identity() -> Self {
+        [[1, 0, 0], [0, 1, 0], [0, 0, 1]]
+    }
+
+    fn matmul(&self, rhs: &Mat3x3) -> Mat3x3 {
+        let columns = [rhs.column(0), rhs.column(1), rhs.column(2)];
+        self.map(|row| columns.map(|column| row.dot(&column)))
+    }
+
+    fn transpose(&self) -> Self {
+        [self.column(0), self.column(1), self.column(2)]
+    }
+
+    fn column(&self, index: usize) -> Vec3 {
+        [self[0][index], self[1][index], self[2][index]]
+    }
+
+    fn determinant(&self) -> i64 {
+        let [a, b, c] = *self;
+        a[0] * (b[1] * c[2] - b[2] * c[1]) - a[1] * (b[0] * c[2] - b[2] * c[0])
+            + a[2] * (b[0] * c[1] - b[1] * c[0])
+    }
+}
+
+/// Enumerate the 24 rotations mapping the coordinate axes onto themselves
+pub fn rotation_group() -> Vec<Mat3x3> {
+    [0, 1, 2]
+        .into_iter()
+        .permutations(3)
+        .cartesian_product(0..8)
+        .map(|(permutation, signs)| {
+            let mut matrix = [[0; 3]; 3];
+            for (row, &axis) in permutation.iter().enumerate() {
+                matrix[row][axis] = if signs & (1 << row) != 0 { -1 } else { 1 };
+            }
+            matrix
+        })
+        .filter(|matrix| matrix.determinant() == 1)
+        .collect()
+}
@@ -0,0 +1,236 @@
+use crate::dag::Dag;
+use crate::*;
+
+use eyre::{bail, ensure};
+use itertools::Itertools;
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::hash::Hash;
+
+/// Policy choosing which ready task is started first when several workers are free
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TieBreak {
+    /// Smallest key first (alphabetical order for letters)
+    Key,
+    /// Largest key first
+    ReverseKey,
+    /// Cheapest task first, then smallest key
+    ShortestFirst,
+    /// Most expensive task first, then smallest key
+    LongestFirst,
+    /// Task with the most expensive chain of dependents first, then smallest key
+    CriticalPath,
+}
+
+impl TieBreak {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name {
+            "key" | "alphabetical" => Ok(Self::Key),
+            "reverse" => Ok(Self::ReverseKey),
+            "shortest" => Ok(Self::ShortestFirst),
+            "longest" => Ok(Self::LongestFirst),
+            "critical-path" => Ok(Self::CriticalPath),
+            _ => bail!("unknown tie-breaking policy: {name}"),
+        }
+    }
+}
+
+/// Execution of a task by a worker, from `start` included to `end` excluded
+#[derive(Copy, Clone, Debug)]
+pub struct Assignment<K> {
+    pub task: K,
+    pub worker: usize,
+    pub start: u64,
+    pub end: u64,
+}
+
+/// Gantt timeline of all tasks, sorted by start time
+pub struct Timeline<K> {
+    pub worker_count: usize,
+    pub assignments: Vec<Assignment<K>>,
+}
+
+impl<K: Copy> Timeline<K> {
+    /// Time at which all tasks are finished
+    pub fn makespan(&self) -> u64 {
+        (self.assignments.iter())
+            .map(|assignment| assignment.end)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Tasks in the order they were started
+    pub fn order(&self) -> Vec<K> {
+        self.assignments.iter().map(|x| x.task).collect()
+    }
+
+    /// Render the timeline as a table with one row per time unit, showing the task of each worker
+    /// and the tasks done so far
+    pub fn render(&self, label: impl Fn(K) -> String) -> String {
+        let labels = (self.assignments.iter())
+            .map(|assignment| label(assignment.task))
+            .collect_vec();
+
+        let width = (labels.iter().map(String::len))
+            .chain(["Worker 00".len()])
+            .max()
+            .unwrap_or(0);
+
+        let mut lines = vec![format!(
+            "{:>6}   {}   Done",
+            "Time",
+            (1..=self.worker_count)
+                .map(|worker| format!("{:^width$}", format!("Worker {worker}")))
+                .join("   ")
+        )];
+
+        for time in 0..self.makespan() {
+            let mut cells = vec!["."; self.worker_count];
+            let mut done = Vec::new();
+
+            for (assignment, label) in self.assignments.iter().zip(&labels) {
+                if (assignment.start..assignment.end).contains(&time) {
+                    cells[assignment.worker] = label;
+                }
+                if assignment.end <= time {
+                    done.push((assignment.end, label));
+                }
+            }
+
+            done.sort_by_key(|&(end, _)| end);
+
+            let line = format!(
+                "{time:>6}   {}   {}",
+                cells
+                    .iter()
+                    .map(|cell| format!("{cell:^width$}"))
+                    .join("   "),
+                done.iter().map(|(_, label)| label).join("")
+            );
+            lines.push(line.trim_end().to_string());
+        }
+
+        lines.join("\n")
+    }
+}
+
+/// Length of the most expensive chain of dependents starting at each task, including the task itself
+fn critical_path_lengths<K, N>(dag: &Dag<K, N>, costs: &HashMap<K, u64>) -> Result<HashMap<K, u64>>
+where
+    K: Copy + Eq + Hash + Debug,
+{
+    let mut lengths = HashMap::new();
+
+    for key in dag.topological_order()?.into_iter().rev() {
+        let longest_dependent = (dag.dependents(key).iter())
+            .map(|dependent| lengths[dependent])
+            .max()
+            .unwrap_or(0);
+        lengths.insert(key, costs[&key] + longest_dependent);
+    }
+
+    Ok(lengths)
+}
+
+/// Run the tasks of a DAG with a number of workers, where a task can start once all its
+/// dependencies are finished, and each free worker starts a ready task as soon as possible
+pub fn schedule<K, N>(
+    dag: &Dag<K, N>,
+    worker_count: usize,
+    cost: impl Fn(K, &N) -> u64,
+    tie_break: TieBreak,
+) -> Result<Timeline<K>>
+where
+    K: Copy + Eq + Hash + Ord + Debug,
+{
+    ensure!(worker_count > 0, "at least one worker is needed");
+
+    // Fails on cycles and unknown dependencies
+    dag.topological_order()?;
+
+    let costs: HashMap<_, _> = dag
+        .iter()
+        .map(|(key, node)| (key, cost(key, node)))
+        .collect();
+
+    let critical_paths = match tie_break {
+        TieBreak::CriticalPath => critical_path_lengths(dag, &costs)?,
+        _ => HashMap::new(),
+    };
+
+    // Ordering where the task to start first is the smallest
+    let compare = |a: &K, b: &K| match tie_break {
+        TieBreak::Key => a.cmp(b),
+        TieBreak::ReverseKey => b.cmp(a),
+        TieBreak::ShortestFirst => costs[a].cmp(&costs[b]).then(a.cmp(b)),
+        TieBreak::LongestFirst => costs[b].cmp(&costs[a]).then(a.cmp(b)),
+        TieBreak::CriticalPath => (critical_paths[b].cmp(&critical_paths[a])).then(a.cmp(b)),
+    };
+
+    let mut remaining_dependencies: HashMap<_, _> = (dag.iter())
+        .map(|(key, _)| (key, dag.dependencies(key).iter().unique().count()))
+        .collect();
+
+    let mut ready = (remaining_dependencies.iter())
+        .filter(|&(_, &count)| count == 0)
+        .map(|(&key, _)| key)
+        .collect_vec();
+
+    let mut workers: Vec<Option<Assignment<K>>> = vec![None; worker_count];
+    let mut assignments = Vec::with_capacity(dag.len());
+    let mut time = 0;
+
+    loop {
+        ready.sort_unstable_by(|a, b| compare(b, a));
+
+        for (worker, slot) in workers.iter_mut().enumerate() {
+            if slot.is_some() {
+                continue;
+            }
+
+            let Some(task) = ready.pop() else {
+                break;
+            };
+
+            let assignment = Assignment {
+                task,
+                worker,
+                start: time,
+                end: time + costs[&task],
+            };
+
+            *slot = Some(assignment);
+            assignments.push(assignment);
+        }
+
+        let Some(next_time) = workers.iter().flatten().map(|x| x.end).min() else {
+            break;
+        };
+
+        time = next_time;
+
+        let mut finished = (workers.iter_mut())
+            .filter(|slot| slot.is_some_and(|x| x.end == time))
+            .filter_map(|slot| slot.take())
+            .map(|x| x.task)
+            .collect_vec();
+
+        finished.sort_unstable();
+
+        for task in finished {
+            for &dependent in dag.dependents(task) {
+                let count = remaining_dependencies.get_mut(&dependent).value()?;
+                *count -= 1;
+                if *count == 0 {
+                    ready.push(dependent);
+                }
+            }
+        }
+    }
+
+    Ok(Timeline {
+        worker_count,
+        assignments,
+    })
+}
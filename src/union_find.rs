@@ -0,0 +1,67 @@
+/// Disjoint-set forest with path compression and union by size
+#[derive(Clone)]
+pub struct UnionFind {
+    parents: Vec<usize>,
+    sizes: Vec<usize>,
+    set_count: usize,
+}
+
+impl UnionFind {
+    pub fn new(size: usize) -> Self {
+        Self {
+            parents: (0..size).collect(),
+            sizes: vec![1; size],
+            set_count: size,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.parents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parents.is_empty()
+    }
+
+    /// Number of disjoint sets
+    pub fn set_count(&self) -> usize {
+        self.set_count
+    }
+
+    pub fn find(&mut self, mut x: usize) -> usize {
+        let mut root = x;
+        while self.parents[root] != root {
+            root = self.parents[root];
+        }
+
+        while self.parents[x] != root {
+            let next = self.parents[x];
+            self.parents[x] = root;
+            x = next;
+        }
+
+        root
+    }
+
+    /// Merge the sets of two elements, returning `false` if they were already in the same set
+    pub fn union(&mut self, x: usize, y: usize) -> bool {
+        let (mut root_x, mut root_y) = (self.find(x), self.find(y));
+        if root_x == root_y {
+            return false;
+        }
+
+        if self.sizes[root_x] < self.sizes[root_y] {
+            std::mem::swap(&mut root_x, &mut root_y);
+        }
+
+        self.parents[root_y] = root_x;
+        self.sizes[root_x] += self.sizes[root_y];
+        self.set_count -= 1;
+        true
+    }
+
+    pub fn set_size(&mut self, x: usize) -> usize {
+        let root = self.find(x);
+        self.sizes[root]
+    }
+}